@@ -0,0 +1,446 @@
+use crate::Seo;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+pub const CONFIG_FILE: &str = "rey.toml";
+
+/// Project configuration read from `rey.toml`.
+///
+/// Every table is optional so that an existing `rey.toml` holding only `[scripts]`
+/// keeps working.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub site: SiteConfig,
+    /// `[seo]` : site-wide defaults of the `Seo` of every page: author, lang,
+    /// og_image, og_type, twitter_card and noindex.
+    pub seo: Seo,
+    pub build: BuildConfig,
+    pub feeds: FeedsConfig,
+    /// `[[taxonomies]]` : `tags` and `categories` unless set.
+    pub taxonomies: Vec<TaxonomyConfig>,
+    pub pagination: PaginationConfig,
+    /// `[permalinks]` : URL pattern of the pages of a section, by section name,
+    /// e.g. `blog = "/:year/:month/:slug/"`.
+    pub permalinks: HashMap<String, String>,
+    pub related: RelatedConfig,
+    pub markdown: MarkdownConfig,
+    pub highlight: HighlightConfig,
+    pub sitemap: SitemapConfig,
+    pub robots: RobotsConfig,
+    pub scripts: HashMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            site: SiteConfig::default(),
+            seo: Seo::default(),
+            build: BuildConfig::default(),
+            feeds: FeedsConfig::default(),
+            taxonomies: vec![
+                TaxonomyConfig::new("tags"),
+                TaxonomyConfig::new("categories"),
+            ],
+            pagination: PaginationConfig::default(),
+            permalinks: HashMap::new(),
+            related: RelatedConfig::default(),
+            markdown: MarkdownConfig::default(),
+            highlight: HighlightConfig::default(),
+            sitemap: SitemapConfig::default(),
+            robots: RobotsConfig::default(),
+            scripts: HashMap::new(),
+        }
+    }
+}
+
+/// `[site]` : global values exposed to every template as `site`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiteConfig {
+    pub title: String,
+    pub description: String,
+    /// URL the site is published at; `rey build` refuses it unset or local.
+    pub base_url: String,
+    /// IANA time zone of the dates shown by the `date` filter, e.g. `Europe/Paris`.
+    pub timezone: Option<String>,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            description: String::new(),
+            base_url: "http://localhost:8000".to_string(),
+            timezone: None,
+        }
+    }
+}
+
+/// `[build]` : options of the `rey build` command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuildConfig {
+    /// Output directory (`dist` by default).
+    pub output_dir: String,
+    /// Template directories never rendered as pages (layouts, partials…).
+    pub ignore: Vec<String>,
+    /// Render the pages marked `draft = true`.
+    pub drafts: bool,
+    /// Render the pages whose date is still to come.
+    pub future: bool,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            output_dir: "dist".to_string(),
+            ignore: vec![
+                "admin".to_string(),
+                "layouts".to_string(),
+                "partials".to_string(),
+                "macros".to_string(),
+            ],
+            drafts: false,
+            future: false,
+        }
+    }
+}
+
+/// `[feeds]` : RSS 2.0 (`rss.xml`), Atom (`atom.xml`) and JSON Feed 1.1 (`feed.json`)
+/// feeds of the dated pages, for the whole site and for each taxonomy term.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedsConfig {
+    pub rss: bool,
+    pub atom: bool,
+    pub json: bool,
+    /// Taxonomies of `[[taxonomies]]` getting one feed per term, e.g. `/tags/rust/atom.xml`.
+    pub taxonomies: Vec<String>,
+    /// Maximum number of entries per feed.
+    pub limit: usize,
+    /// Whole rendered page in each entry instead of its summary.
+    pub full_content: bool,
+    /// Include the pages marked `draft = true`, when the build renders them
+    /// (`--drafts`).
+    pub drafts: bool,
+    /// Sections whose pages are syndicated; empty means every section.
+    pub sections: Vec<String>,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        Self {
+            rss: true,
+            atom: true,
+            json: true,
+            taxonomies: vec!["tags".to_string(), "categories".to_string()],
+            limit: 20,
+            full_content: false,
+            drafts: false,
+            sections: Vec::new(),
+        }
+    }
+}
+
+/// One `[[taxonomies]]` entry: a front matter list (or `Seo::keywords` for `tags`)
+/// whose terms get an index page at `/{name}/` and a list page at `/{name}/{term}/`.
+///
+/// ```toml
+/// [[taxonomies]]
+/// name = "authors"
+/// paginate = 20
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaxonomyConfig {
+    pub name: String,
+    /// Displayed name, `Tags` for `tags` when unset.
+    pub title: Option<String>,
+    /// Pages per term list page.
+    pub paginate: usize,
+    /// URL pattern of the term list pages after the first, `[pagination] path` by default.
+    pub paginate_path: Option<String>,
+    /// Template of the term index (`taxonomy` by default).
+    pub template: Option<String>,
+    /// Template of the term list pages (`term` by default).
+    pub term_template: Option<String>,
+}
+
+impl TaxonomyConfig {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for TaxonomyConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            title: None,
+            paginate: 10,
+            paginate_path: None,
+            template: None,
+            term_template: None,
+        }
+    }
+}
+
+/// `[pagination]` : how section and term listings are split into pages.
+///
+/// A section opts in with `paginate_by = 10` in its `_index.md`, and may override
+/// the URL pattern with `paginate_path`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaginationConfig {
+    /// Pages per listing page for the sections without `paginate_by`; `0` keeps
+    /// them on a single page.
+    pub per_page: usize,
+    /// URL of the listing pages after the first, relative to the listing;
+    /// `:num` is the page number.
+    pub path: String,
+    /// Page numbers shown on each side of the current one.
+    pub window: usize,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            per_page: 0,
+            path: "page/:num/".to_string(),
+            window: 2,
+        }
+    }
+}
+
+/// `[related]` : the `related` pages given to the template of each page, scored by
+/// shared tags and keywords, and by series.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelatedConfig {
+    /// Maximum number of related pages, `0` to disable them.
+    pub limit: usize,
+    /// Score of a shared tag or keyword.
+    pub term_weight: usize,
+    /// Score of a shared series.
+    pub series_weight: usize,
+}
+
+impl Default for RelatedConfig {
+    fn default() -> Self {
+        Self {
+            limit: 5,
+            term_weight: 1,
+            series_weight: 3,
+        }
+    }
+}
+
+/// `[markdown]` : dialect and rendering of the markdown of `content/` and of the
+/// `markdown` and `md_excerpt` filters (comrak options).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkdownConfig {
+    /// Clickable `#` link at the end of every heading.
+    pub anchors: bool,
+    /// Shallowest heading level listed in `page.toc`.
+    pub toc_min_depth: u8,
+    /// Deepest heading level listed in `page.toc`.
+    pub toc_max_depth: u8,
+    /// Words of `page.summary` when the page has no `<!-- more -->`.
+    pub summary_words: usize,
+    /// Reading speed behind `page.reading_time`.
+    pub words_per_minute: usize,
+    /// Reading speed of Chinese and Japanese, in characters.
+    pub cjk_per_minute: usize,
+    /// GFM tables.
+    pub table: bool,
+    /// `~~struck~~` text.
+    pub strikethrough: bool,
+    /// `[^1]` footnotes, listed at the end of the document.
+    pub footnotes: bool,
+    /// `- [x] done` task lists.
+    pub tasklist: bool,
+    /// Bare URLs and `www.` links made clickable.
+    pub autolink: bool,
+    /// `e = mc^2^`.
+    pub superscript: bool,
+    /// `Term` then `: definition` description lists.
+    pub description_lists: bool,
+    /// Escapes the HTML tags GFM disallows (`<script>`, `<iframe>`, …) in raw HTML.
+    pub tagfilter: bool,
+    /// Front matter delimiter skipped by the `markdown` filter, e.g. `"---"`.
+    pub front_matter_delimiter: Option<String>,
+    /// Curly quotes, `–`, `—` and `…` from their ASCII forms.
+    pub smart: bool,
+    /// Soft line breaks rendered as `<br>`.
+    pub hardbreaks: bool,
+    /// Raw HTML and dangerous URLs kept as is instead of omitted.
+    #[serde(rename = "unsafe")]
+    pub unsafe_html: bool,
+    /// Raw HTML escaped rather than omitted, when not `unsafe`.
+    pub escape: bool,
+    /// `<pre lang="rust">` instead of `<code class="language-rust">`.
+    pub github_pre_lang: bool,
+    /// Images with a title wrapped in `<figure>` with the title as `<figcaption>`.
+    pub figure_with_caption: bool,
+    /// `:rocket:` shortcodes turned into emoji; a page can opt out with `emoji = false`.
+    pub emoji: bool,
+    /// Emoji wrapped in `<span role="img" aria-label="rocket">`.
+    pub emoji_aria: bool,
+    /// Typographic fixes of the `typo` filter applied to all content, in the
+    /// language of each page (`lang`, then `[seo] lang`).
+    pub typo: bool,
+}
+
+impl Default for MarkdownConfig {
+    fn default() -> Self {
+        Self {
+            anchors: false,
+            toc_min_depth: 2,
+            toc_max_depth: 3,
+            summary_words: 40,
+            words_per_minute: 200,
+            cjk_per_minute: 500,
+            table: true,
+            strikethrough: true,
+            footnotes: false,
+            tasklist: false,
+            autolink: false,
+            superscript: false,
+            description_lists: false,
+            tagfilter: false,
+            front_matter_delimiter: None,
+            smart: false,
+            hardbreaks: false,
+            unsafe_html: false,
+            escape: false,
+            github_pre_lang: false,
+            figure_with_caption: false,
+            emoji: true,
+            emoji_aria: false,
+            typo: false,
+        }
+    }
+}
+
+/// `[highlight]` : server-side highlighting of fenced code blocks, styled by the
+/// stylesheet `rey highlight` exports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighlightConfig {
+    pub enabled: bool,
+    /// Number every line; a block can also ask for it with ```` ```rust linenos ````.
+    pub line_numbers: bool,
+    /// Theme of the exported stylesheet (see `rey highlight --list`).
+    pub theme: String,
+    /// Theme used under `prefers-color-scheme: dark`, if any.
+    pub dark_theme: Option<String>,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            line_numbers: false,
+            theme: "InspiredGitHub".to_string(),
+            dark_theme: Some("base16-ocean.dark".to_string()),
+        }
+    }
+}
+
+/// `[sitemap]` : `sitemap.xml` of every published page, split into `sitemap-N.xml`
+/// files under a sitemap index past 50 000 URLs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SitemapConfig {
+    pub enabled: bool,
+    /// `<changefreq>` of every URL (`daily`, `weekly`…), omitted when unset.
+    pub changefreq: Option<String>,
+    /// URL paths left out, e.g. `/404.html`; a trailing `*` matches a prefix.
+    pub exclude: Vec<String>,
+}
+
+impl Default for SitemapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            changefreq: None,
+            exclude: vec!["/404.html".to_string()],
+        }
+    }
+}
+
+/// `[robots]` : `robots.txt`, ending with the URL of the sitemap.
+///
+/// ```toml
+/// [[robots.rules]]
+/// user_agent = "*"
+/// disallow = ["/admin/"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RobotsConfig {
+    pub enabled: bool,
+    pub rules: Vec<RobotsRule>,
+}
+
+impl Default for RobotsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: vec![RobotsRule::default()],
+        }
+    }
+}
+
+/// One `User-agent` group of `robots.txt`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RobotsRule {
+    pub user_agent: String,
+    pub allow: Vec<String>,
+    pub disallow: Vec<String>,
+    pub crawl_delay: Option<u32>,
+}
+
+impl Default for RobotsRule {
+    fn default() -> Self {
+        Self {
+            user_agent: "*".to_string(),
+            allow: Vec::new(),
+            disallow: Vec::new(),
+            crawl_delay: None,
+        }
+    }
+}
+
+impl Config {
+    /// Loads `rey.toml` from the current directory, falling back to defaults when absent.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::from_file(Path::new(CONFIG_FILE))
+    }
+
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        let config = toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("failed to parse {}: {e}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Absolute URL for a site path, built on `site.base_url`.
+    pub fn url(&self, path: &str) -> String {
+        let base = self.site.base_url.trim_end_matches('/');
+        if path.starts_with('/') {
+            format!("{base}{path}")
+        } else {
+            format!("{base}/{path}")
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{
+    console::{Shell, console::Capsule},
+    content::Publish,
+};
+use clap::Args;
+
+#[doc = "Render the site to static HTML, ready for static hosting."]
+#[derive(Args)]
+pub struct Build {
+    /// Output directory (defaults to `[build] output_dir` from rey.toml, then `dist`).
+    #[arg(short, long)]
+    pub out: Option<String>,
+    /// Render the pages marked `draft = true`.
+    #[arg(long)]
+    pub drafts: bool,
+    /// Render the pages whose date is still to come.
+    #[arg(long)]
+    pub future: bool,
+}
+
+impl Shell for Build {
+    fn run(&self) {
+        Capsule::build(
+            self.out.as_deref(),
+            Publish {
+                drafts: self.drafts,
+                future: self.future,
+            },
+        );
+    }
+}
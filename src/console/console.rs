@@ -9,6 +9,15 @@ use crate::console::{
     },
     watchers,
 };
+use crate::{
+    config::{BuildConfig, Config},
+    content::{DRAFTS_VAR, FUTURE_VAR, Publish, Status},
+    render::highlight,
+    site::{
+        self, Site,
+        audit::{self, Level},
+    },
+};
 use anyhow::Ok;
 use inquire::Editor;
 use std::{
@@ -16,6 +25,7 @@ use std::{
     path::Path,
     process::Command,
 };
+use tabled::{builder::Builder, settings::Style};
 
 pub struct Capsule;
 
@@ -110,6 +120,13 @@ impl Capsule {
             if Path::new("public/").exists() {
                 remove_dir_all("public/").expect("failed to remove public directory");
             }
+            let out = Config::load()
+                .map(|c| c.build.output_dir)
+                .unwrap_or_else(|_| BuildConfig::default().output_dir);
+            let cwd = std::env::current_dir().expect("failed to read the current directory");
+            if Path::new(&out).exists() && site::check_output_dir(&cwd, Path::new(&out)).is_ok() {
+                remove_dir_all(&out).expect("failed to remove output directory");
+            }
             if Path::new("target/").exists() {
                 remove_dir_all("target/").expect("failed to remove target directory");
             }
@@ -166,7 +183,7 @@ impl Capsule {
         println!("View created: {}", view);
     }
 
-    pub fn serve() {
+    pub fn serve(publish: Publish) {
         ok_clear("Serving the site locally...", true);
         if !is_initialized() {
             ok_clear(
@@ -179,8 +196,133 @@ impl Capsule {
         ok_command(
             "serving the site...",
             true,
-            std::process::Command::new("cargo").arg("run"),
+            std::process::Command::new("cargo")
+                .arg("run")
+                .env(DRAFTS_VAR, if publish.drafts { "1" } else { "0" })
+                .env(FUTURE_VAR, if publish.future { "1" } else { "0" }),
+        );
+    }
+
+    pub fn build(out: Option<&str>, publish: Publish) {
+        ok_clear("Building the site...", true);
+        let site = Site::load_with(publish).unwrap_or_else(|e| {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        });
+        let out = out.unwrap_or(&site.config.build.output_dir).to_string();
+        let report = site.build(Path::new(&out)).unwrap_or_else(|e| {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        });
+        ok_clear(
+            format!(
+                "{} pages, {} files and {} assets written to {out}/",
+                report.pages, report.files, report.assets
+            )
+            .as_str(),
+            false,
+        );
+        Capsule::pending(&site);
+    }
+
+    /// Table of the drafts, scheduled and expired pages of the site.
+    pub fn pending(site: &Site) {
+        let pending = site.pending();
+        if pending.is_empty() {
+            return;
+        }
+        let format = |d: Option<chrono::DateTime<chrono::Utc>>| {
+            d.map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        let mut builder = Builder::new();
+        builder.push_record(["Page", "Status", "Goes live", "Rendered"]);
+        for (page, status) in &pending {
+            let live = match status {
+                Status::Expired => format!("expired {}", format(page.expires_at())),
+                _ => format(page.published()),
+            };
+            let rendered = site.pages().any(|p| p.path == page.path);
+            builder.push_record([
+                page.path.clone(),
+                status.to_string(),
+                live,
+                if rendered { "yes" } else { "no" }.to_string(),
+            ]);
+        }
+        let mut table = builder.build();
+        table.with(Style::modern());
+        println!("{table}");
+    }
+
+    /// Writes the SCSS of the highlight themes to `out`, the themes defaulting to
+    /// `[highlight]`.
+    pub fn highlight(
+        out: &str,
+        theme: Option<&str>,
+        dark_theme: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut config = Config::load()?.highlight;
+        if let Some(theme) = theme {
+            config.theme = theme.to_string();
+        }
+        if let Some(dark) = dark_theme {
+            config.dark_theme = Some(dark.to_string()).filter(|d| !d.is_empty());
+        }
+        site::write(Path::new(out), &highlight::scss(&config)?)?;
+        ok_clear(format!("highlight theme written to {out}").as_str(), false);
+        Ok(())
+    }
+
+    pub fn highlight_themes() {
+        let mut builder = Builder::new();
+        builder.push_record(["Theme"]);
+        let mut themes = highlight::themes();
+        themes.sort();
+        for theme in themes {
+            builder.push_record([theme]);
+        }
+        let mut table = builder.build();
+        table.with(Style::modern());
+        println!("{table}");
+    }
+
+    pub fn seo_audit(strict: bool) {
+        let site = Site::load().unwrap_or_else(|e| {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        });
+        let entries = site.seo_entries();
+        let issues = audit::audit(&entries);
+        if issues.is_empty() {
+            ok_clear(
+                format!("{} pages audited, no issue found.", entries.len()).as_str(),
+                false,
+            );
+            return;
+        }
+        let mut builder = Builder::new();
+        builder.push_record(["Page", "Level", "Issue"]);
+        for issue in &issues {
+            builder.push_record([
+                issue.path.clone(),
+                issue.level.to_string(),
+                issue.message.clone(),
+            ]);
+        }
+        let mut table = builder.build();
+        table.with(Style::modern());
+        println!("{table}");
+
+        let errors = issues.iter().filter(|i| i.level == Level::Error).count();
+        let warnings = issues.len() - errors;
+        println!(
+            "{} pages audited: {errors} errors, {warnings} warnings.",
+            entries.len()
         );
+        if errors > 0 || (strict && warnings > 0) {
+            std::process::exit(1);
+        }
     }
 
     pub fn watch() -> anyhow::Result<()> {
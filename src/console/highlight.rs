@@ -0,0 +1,33 @@
+use crate::console::{Shell, console::Capsule};
+use clap::Args;
+
+#[doc = "Export the code highlighting theme as SCSS, for the class-based highlighting of code blocks."]
+#[derive(Args)]
+pub struct Highlight {
+    /// List the bundled themes instead.
+    #[arg(short, long)]
+    pub list: bool,
+    /// Light theme (defaults to `[highlight] theme` from rey.toml).
+    #[arg(long)]
+    pub theme: Option<String>,
+    /// Dark theme (defaults to `[highlight] dark_theme` from rey.toml).
+    #[arg(long)]
+    pub dark_theme: Option<String>,
+    /// Output file, to be imported by `web.scss`.
+    #[arg(short, long, default_value = "front/web/scss/_highlight.scss")]
+    pub out: String,
+}
+
+impl Shell for Highlight {
+    fn run(&self) {
+        if self.list {
+            Capsule::highlight_themes();
+            return;
+        }
+        Capsule::highlight(&self.out, self.theme.as_deref(), self.dark_theme.as_deref())
+            .unwrap_or_else(|e| {
+                eprintln!("Error: {e:#}");
+                std::process::exit(1);
+            });
+    }
+}
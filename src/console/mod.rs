@@ -4,13 +4,17 @@ pub trait Shell {
     fn run(&self);
 }
 
+pub mod build;
 pub mod clean;
+#[allow(clippy::module_inception)]
 pub mod console;
 pub mod edit;
+pub mod highlight;
 pub mod init;
 pub mod prompts;
 pub mod runs;
 pub mod scan;
+pub mod seo;
 pub mod serve;
 pub mod views;
 pub mod watch;
@@ -18,11 +22,14 @@ pub mod watchers;
 
 #[derive(Subcommand)]
 pub enum Commands {
+    Build(build::Build),
     Clean(clean::Clean),
     Edit(edit::Edit),
+    Highlight(highlight::Highlight),
     Init(init::Init),
     Run(runs::Run),
     Scan(scan::Scan),
+    Seo(seo::Seo),
     Watch(watch::Watch),
     Serve(serve::Serve),
 }
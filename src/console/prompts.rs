@@ -108,6 +108,7 @@ impl Licenses {
             "None".to_string(),
         ]
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s {
             "MIT" => Licenses::MIT,
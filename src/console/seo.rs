@@ -0,0 +1,27 @@
+use crate::console::{Shell, console::Capsule};
+use clap::{Args, Subcommand};
+
+#[doc = "Search engine optimization tools."]
+#[derive(Args)]
+pub struct Seo {
+    #[command(subcommand)]
+    pub command: SeoCommands,
+}
+
+#[derive(Subcommand)]
+pub enum SeoCommands {
+    /// Report too long, missing or duplicate titles and descriptions and invalid social metadata.
+    Audit {
+        /// Also exit with a non-zero status when only warnings are found.
+        #[arg(long)]
+        strict: bool,
+    },
+}
+
+impl Shell for Seo {
+    fn run(&self) {
+        match &self.command {
+            SeoCommands::Audit { strict } => Capsule::seo_audit(*strict),
+        }
+    }
+}
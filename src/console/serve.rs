@@ -1,12 +1,25 @@
-use crate::console::{Shell, console::Capsule};
+use crate::{
+    console::{Shell, console::Capsule},
+    content::Publish,
+};
 use clap::Args;
 
 #[doc = "Start a local development server to serve the project."]
 #[derive(Args)]
-pub struct Serve;
+pub struct Serve {
+    /// Publish the pages marked `draft = true`.
+    #[arg(long)]
+    pub drafts: bool,
+    /// Publish the pages whose date is still to come.
+    #[arg(long)]
+    pub future: bool,
+}
 
 impl Shell for Serve {
     fn run(&self) {
-        Capsule::serve();
+        Capsule::serve(Publish {
+            drafts: self.drafts,
+            future: self.future,
+        });
     }
 }
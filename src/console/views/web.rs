@@ -251,6 +251,34 @@ pub fn scan() {
     println!("{}", table);
 }
 
+/// `templates/index.html.tera` of a new project, rendered by both the Rocket app
+/// and `rey build`: it only reads the `title` they both provide.
+pub const INDEX_TEMPLATE: &str = r#"<!doctype html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8"/>
+    <meta name="viewport" content="width=device-width, initial-scale=1"/>
+    <title>{{ title }}</title>
+    <link rel="stylesheet" href="/public/css/web.css" type="text/css"/>
+  </head>
+  <body>
+    <h1>{{ title }}</h1>
+    <p>It works!</p>
+    <script type="module" src="/public/js/web.js"></script>
+  </body>
+</html>
+"#;
+
+/// `templates/404.html.tera` of a new project.
+pub const NOT_FOUND_TEMPLATE: &str = r#"<!doctype html>
+<html><head><meta charset="utf-8"><title>404</title></head>
+<body>
+  <h1>404</h1>
+  <p>Page introuvable: {{ path | default(value="") }}</p>
+  <p><a href="/">Retour</a></p>
+</body></html>
+"#;
+
 pub fn generate_main_rs() -> std::io::Result<()> {
     create_dir_all("src")?;
     create_dir_all("public")?;
@@ -271,7 +299,6 @@ struct PingResponse<'a> {
 fn index() -> Template {
     Template::render("index", context! {
         title: "Hello Rocket + Tera",
-        message: "It works!",
     })
 }
 
@@ -305,36 +332,11 @@ async fn main() -> Result<(), rocket::Error> {
     // génère un template minimal pour démarrer
     create_dir_all("templates")?;
     let mut t = File::create("templates/index.html.tera")?;
-    t.write_all(
-        br#"<!doctype html>
-<html lang="en">
-  <head>
-    <meta charset="utf-8"/>
-    <meta name="viewport" content="width=device-width, initial-scale=1"/>
-    <title>{{ title }}</title>
-    <link rel="stylesheet" href="/public/css/web.css" type="text/css"/>
-  </head>
-  <body>
-    <h1>{{ title }}</h1>
-    <p>{{ message }}</p>
-    <script type="module" src="/public/js/web.js"></script>
-  </body>
-</html>
-"#,
-    )?;
+    t.write_all(INDEX_TEMPLATE.as_bytes())?;
     t.sync_all()?;
 
     let mut t404 = File::create("templates/404.html.tera")?;
-    t404.write_all(
-        br#"<!doctype html>
-<html><head><meta charset="utf-8"><title>404</title></head>
-<body>
-  <h1>404</h1>
-  <p>Page introuvable: {{ path | default(value="") }}</p>
-  <p><a href="/">Retour</a></p>
-</body></html>
-"#,
-    )?;
+    t404.write_all(NOT_FOUND_TEMPLATE.as_bytes())?;
     t404.sync_all()?;
 
     Ok(())
@@ -369,7 +371,7 @@ pub fn init() -> Result<(), std::io::Error> {
 
     let mut hgignore = File::create(".hgignore")?;
 
-    hgignore.write_all(b"syntax: glob\n/target\n/node_modules\n/front/web/node_modules\n/front/admin/node_modules\n.DS_Store\n")?;
+    hgignore.write_all(b"syntax: glob\n/target\n/dist\n/node_modules\n/front/web/node_modules\n/front/admin/node_modules\n.DS_Store\n")?;
     hgignore.sync_all()?;
 
     rocket_toml.write_all(b"[default]\naddress = \"0.0.0.0\"\nport = 8000\n")?;
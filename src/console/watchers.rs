@@ -38,25 +38,21 @@ fn is_ignored_file(path: &Path) -> bool {
     {
         return true;
     }
-    match path
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or_default()
-    {
-        "css" | "js" | "map" | "swp" | "swo" | "tmp" => true,
-        _ => false,
-    }
+    matches!(
+        path.extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default(),
+        "css" | "js" | "map" | "swp" | "swo" | "tmp"
+    )
 }
 
 fn is_watched_source(path: &Path) -> bool {
-    match path
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or_default()
-    {
-        "rs" | "ts" | "tsx" | "scss" | "sass" | "toml" => true,
-        _ => false,
-    }
+    matches!(
+        path.extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default(),
+        "rs" | "ts" | "tsx" | "scss" | "sass" | "toml"
+    )
 }
 
 pub fn watch() -> Result<(), Box<dyn std::error::Error>> {
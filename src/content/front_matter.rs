@@ -0,0 +1,133 @@
+use crate::Seo;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Metadata block at the top of a markdown file, delimited by `+++` (TOML)
+/// or `---` (YAML).
+///
+/// Unknown keys are kept in `extra` and exposed to templates as `page.extra`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct FrontMatter {
+    pub title: String,
+    pub description: String,
+    pub date: Option<String>,
+    pub updated: Option<String>,
+    /// Date after which the page is no longer published.
+    pub expires: Option<String>,
+    pub tags: Vec<String>,
+    pub draft: bool,
+    /// Last segment of the URL path, slugified; the file name by default.
+    pub slug: Option<String>,
+    /// Name of the series the page is a part of.
+    pub series: Option<String>,
+    /// Position of the page in its series; parts without one follow, by date.
+    pub part: Option<usize>,
+    /// Pages listed first among the related ones: URL paths, slugs or source files.
+    pub related: Vec<String>,
+    /// Former URL paths of the page, redirected to its current one.
+    pub aliases: Vec<String>,
+    /// `false` to keep the `:name:` emoji shortcodes of the page as they are.
+    pub emoji: Option<bool>,
+    /// Template used to render the page (`page` by default).
+    pub template: Option<String>,
+    /// Pages per listing page of a section (`_index.md` only).
+    pub paginate_by: Option<usize>,
+    /// URL pattern of the listing pages of a section, e.g. `page/:num/`.
+    pub paginate_path: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    /// The same front matter read as a [`Seo`] (`keywords`, `og_image`, `author`…).
+    #[serde(skip)]
+    pub seo: Seo,
+}
+
+impl FrontMatter {
+    /// Splits `source` into its front matter and its markdown body, then parses the
+    /// front matter. A file without front matter gets the default values.
+    pub fn parse(source: &str) -> anyhow::Result<(Self, &str)> {
+        let (raw, body) = split(source);
+        let value = match raw {
+            Some(("+++", raw)) => toml_to_json(toml::from_str::<toml::Value>(raw)?),
+            Some((_, raw)) if raw.trim().is_empty() => Value::Object(Map::new()),
+            Some((_, raw)) => serde_yaml::from_str::<Value>(raw)?,
+            None => Value::Object(Map::new()),
+        };
+        let mut front: Self = serde_json::from_value(value.clone())?;
+        front.seo = serde_json::from_value(value)?;
+        Ok((front, body))
+    }
+}
+
+/// Returns the front matter delimiter and content, if any, and the remaining body.
+pub fn split(source: &str) -> (Option<(&'static str, &str)>, &str) {
+    let source = source.trim_start_matches('\u{feff}');
+    for delim in ["+++", "---"] {
+        let Some(rest) = source.strip_prefix(delim) else {
+            continue;
+        };
+        let Some(rest) = rest
+            .strip_prefix("\r\n")
+            .or_else(|| rest.strip_prefix('\n'))
+        else {
+            continue;
+        };
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == delim {
+                let body = &rest[offset + line.len()..];
+                return (Some((delim, &rest[..offset])), body);
+            }
+            offset += line.len();
+        }
+    }
+    (None, source)
+}
+
+/// TOML dates are not strings once deserialized: convert them to their RFC 3339
+/// representation so every front matter ends up with the same shape.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(a) => Value::Array(a.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(t) => {
+            Value::Object(t.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_and_yaml_front_matter() {
+        let (front, body) = FrontMatter::parse(
+            "+++\ntitle = \"Hi\"\ndate = 2025-01-31\nmood = \"ok\"\n+++\nBody\n",
+        )
+        .unwrap();
+        assert_eq!(front.title, "Hi");
+        assert_eq!(front.date.as_deref(), Some("2025-01-31"));
+        assert_eq!(front.extra["mood"], "ok");
+        assert_eq!(body, "Body\n");
+
+        let (front, body) =
+            FrontMatter::parse("---\r\ntitle: Hi\r\ntags: [a, b]\r\nauthor: Me\r\n---\r\nBody")
+                .unwrap();
+        assert_eq!(front.tags, ["a", "b"]);
+        assert_eq!(front.seo.author.as_deref(), Some("Me"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn no_or_unclosed_front_matter() {
+        assert_eq!(split("# Title\n"), (None, "# Title\n"));
+        assert_eq!(split("---\ntitle: x\n"), (None, "---\ntitle: x\n"));
+        assert_eq!(split("---\n---\nBody"), (Some(("---", "")), "Body"));
+        assert!(FrontMatter::parse("+++\ntitle = \n+++\n").is_err());
+    }
+}
@@ -0,0 +1,603 @@
+//! Markdown content of a rey project.
+//!
+//! Every `.md` file under `content/` becomes a [`Page`]; every directory is a
+//! [`Section`] whose optional `_index.md` holds the section's own front matter.
+//! `content/blog/hello.md` is published at `/blog/hello/`, and
+//! `content/blog/hello/index.md` too, so that a post can keep its images next to it.
+
+use crate::{
+    Seo,
+    config::Config,
+    render::{excerpt, filters, shortcodes, toc::TocEntry, words::Words},
+};
+use anyhow::{Context as _, bail};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use rocket_dyn_templates::tera::Tera;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+pub mod front_matter;
+
+pub use front_matter::FrontMatter;
+
+pub const CONTENT_DIR: &str = "content";
+pub const SECTION_INDEX: &str = "_index.md";
+
+/// A rendered markdown file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Page {
+    pub title: String,
+    pub description: String,
+    pub date: Option<String>,
+    pub updated: Option<String>,
+    pub expires: Option<String>,
+    pub tags: Vec<String>,
+    pub draft: bool,
+    pub slug: String,
+    pub series: Option<String>,
+    pub part: Option<usize>,
+    /// Name of the section the page belongs to (`""` for the root of `content/`).
+    pub section: String,
+    /// Source file, relative to `content/`.
+    pub source: String,
+    /// URL path of the page, e.g. `/blog/hello/`.
+    pub path: String,
+    /// Former URL paths redirecting to `path`.
+    pub aliases: Vec<String>,
+    /// Rendered HTML.
+    pub content: String,
+    /// Rendered HTML of the markdown before `<!-- more -->`, or else the first
+    /// `[markdown] summary_words` words of `content`.
+    pub summary: String,
+    /// Words of `content`, code blocks excluded.
+    pub word_count: usize,
+    /// Minutes to read `content`, at `[markdown] words_per_minute`.
+    pub reading_time: usize,
+    /// Table of contents, from `[markdown] toc_min_depth` to `toc_max_depth`.
+    pub toc: Vec<TocEntry>,
+    pub extra: Map<String, Value>,
+    pub seo: Seo,
+    /// Related pages picked in the front matter, see `site::related`.
+    #[serde(skip)]
+    pub related: Vec<String>,
+    #[serde(skip)]
+    pub raw: String,
+    #[serde(skip)]
+    pub template: Option<String>,
+}
+
+/// A directory of `content/` and the pages it holds.
+#[derive(Debug, Clone, Serialize)]
+pub struct Section {
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub path: String,
+    /// Rendered HTML of `_index.md`, if any.
+    pub content: String,
+    pub extra: Map<String, Value>,
+    pub seo: Seo,
+    /// Pages of the section, newest first.
+    pub pages: Vec<Page>,
+    #[serde(skip)]
+    pub template: Option<String>,
+    #[serde(skip)]
+    pub paginate_by: Option<usize>,
+    #[serde(skip)]
+    pub paginate_path: Option<String>,
+    /// Whether the section has an `_index.md`.
+    #[serde(skip)]
+    pub has_index: bool,
+}
+
+impl Page {
+    /// Builds a page from the source of `file`, relative to the content directory,
+    /// its shortcodes being rendered with `tera` and its markdown as `config` says.
+    pub fn parse(file: &Path, source: &str, tera: &Tera, config: &Config) -> anyhow::Result<Self> {
+        let (front, body) = FrontMatter::parse(source)
+            .with_context(|| format!("invalid front matter in {}", file.display()))?;
+        let section = section_of(file);
+        // Slugifié, le `slug` ne peut pas sortir de sa section (`../x`)
+        let slug = match &front.slug {
+            Some(s) => match slug::slugify(s) {
+                slug if slug.is_empty() => bail!("empty slug `{s}` in {}", file.display()),
+                slug => slug,
+            },
+            None => slug::slugify(file_stem(file)),
+        };
+        let path = if section.is_empty() {
+            format!("/{slug}/")
+        } else {
+            format!("/{section}/{slug}/")
+        };
+        let expanded = expand(tera, file, source, body)?;
+        let markdown = &config.markdown;
+        let (content, toc) = filters::render_markdown_toc(
+            config,
+            &expanded.markdown,
+            markdown.toc_min_depth,
+            markdown.toc_max_depth,
+        );
+        let postprocess = |html: String| postprocess(config, &front, html);
+        let content = expanded.restore(&postprocess(content));
+        let summary = match excerpt::before_more(&expanded.markdown) {
+            Some(before) => expanded.restore(&postprocess(
+                filters::render_markdown_toc(config, before, 1, 0).0,
+            )),
+            None => excerpt::truncate_html(&content, markdown.summary_words),
+        };
+        let words = Words::of_html(&content);
+        Ok(Self {
+            title: front.title,
+            description: front.description,
+            date: front.date,
+            updated: front.updated,
+            expires: front.expires,
+            tags: front.tags,
+            draft: front.draft,
+            slug,
+            series: front.series.filter(|s| !s.trim().is_empty()),
+            part: front.part,
+            section,
+            source: file.to_string_lossy().replace('\\', "/"),
+            path,
+            aliases: front.aliases,
+            content,
+            summary,
+            word_count: words.total(),
+            reading_time: words.reading_time(markdown),
+            toc,
+            extra: front.extra,
+            seo: front.seo,
+            related: front.related,
+            raw: body.to_string(),
+            template: front.template,
+        })
+    }
+
+    /// Terms of the page for the taxonomy `name`: `tags` (falling back to
+    /// `Seo::keywords`) or any front matter list such as `categories`.
+    pub fn terms(&self, name: &str) -> Vec<String> {
+        if name == "tags" {
+            return if self.tags.is_empty() {
+                self.seo.keywords.clone()
+            } else {
+                self.tags.clone()
+            };
+        }
+        match self.extra.get(name) {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(str::to_string)
+                .collect(),
+            Some(Value::String(s)) => vec![s.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Publication date, if the front matter holds a valid one.
+    pub fn published(&self) -> Option<DateTime<Utc>> {
+        self.date.as_deref().and_then(parse_date)
+    }
+
+    /// Moves the page to the URL built from a permalink `pattern` such as
+    /// `/:year/:month/:slug/`: `:year`, `:month` and `:day` come from its date,
+    /// `:section` from its section and `:slug` from `Seo::slug`, then the slugified
+    /// title. A page without date keeps its path when the pattern needs one.
+    pub fn apply_permalink(&mut self, pattern: &str) {
+        let date = self.published();
+        if date.is_none()
+            && [":year", ":month", ":day"]
+                .iter()
+                .any(|p| pattern.contains(p))
+        {
+            return;
+        }
+        let slug = match self.seo.slug.as_deref() {
+            Some(s) if !s.trim().is_empty() => slug::slugify(s),
+            _ if !self.title.trim().is_empty() => slug::slugify(&self.title),
+            _ => self.slug.clone(),
+        };
+        let mut path = pattern.replace(":section", &self.section);
+        if let Some(date) = date {
+            path = path
+                .replace(":year", &date.format("%Y").to_string())
+                .replace(":month", &date.format("%m").to_string())
+                .replace(":day", &date.format("%d").to_string());
+        }
+        path = path.replace(":slug", &slug);
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        self.path = if segments.is_empty() {
+            "/".to_string()
+        } else {
+            format!("/{}/", segments.join("/"))
+        };
+        self.slug = slug;
+    }
+
+    /// Expiry date, if the front matter holds a valid one.
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires.as_deref().and_then(parse_date)
+    }
+
+    /// Where the page stands at `now`: a draft first, then expired, then scheduled
+    /// when its date is still to come.
+    pub fn status(&self, now: DateTime<Utc>) -> Status {
+        if self.draft {
+            Status::Draft
+        } else if self.expires_at().is_some_and(|e| e <= now) {
+            Status::Expired
+        } else if self.published().is_some_and(|d| d > now) {
+            Status::Scheduled
+        } else {
+            Status::Published
+        }
+    }
+}
+
+/// Publication state of a [`Page`], see [`Page::status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Published,
+    Draft,
+    Scheduled,
+    Expired,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Published => write!(f, "published"),
+            Status::Draft => write!(f, "draft"),
+            Status::Scheduled => write!(f, "scheduled"),
+            Status::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// Which pages besides the published ones get rendered. Expired pages never are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Publish {
+    pub drafts: bool,
+    /// Pages whose date is still to come.
+    pub future: bool,
+}
+
+/// Environment variables set by `rey serve --drafts --future` for the app it runs.
+pub const DRAFTS_VAR: &str = "REY_DRAFTS";
+pub const FUTURE_VAR: &str = "REY_FUTURE";
+
+impl Publish {
+    /// `REY_DRAFTS` and `REY_FUTURE`, set by `rey serve --drafts --future`.
+    pub fn from_env() -> Self {
+        Self::from_vars(|k| std::env::var(k).ok())
+    }
+
+    /// Reads the variables of [`Publish::from_env`] with `var`: set, neither empty
+    /// nor `0`.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let set = |k| var(k).is_some_and(|v| !v.is_empty() && v != "0");
+        Self {
+            drafts: set(DRAFTS_VAR),
+            future: set(FUTURE_VAR),
+        }
+    }
+
+    pub fn allows(&self, status: Status) -> bool {
+        match status {
+            Status::Published => true,
+            Status::Draft => self.drafts,
+            Status::Scheduled => self.future,
+            Status::Expired => false,
+        }
+    }
+}
+
+impl Section {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            title: String::new(),
+            description: String::new(),
+            path: if name.is_empty() {
+                "/".to_string()
+            } else {
+                format!("/{name}/")
+            },
+            content: String::new(),
+            extra: Map::new(),
+            seo: Seo::new(),
+            pages: Vec::new(),
+            template: None,
+            paginate_by: None,
+            paginate_path: None,
+            has_index: false,
+        }
+    }
+}
+
+/// Expands the shortcodes of `body`, the markdown after the front matter of `source`.
+fn expand(
+    tera: &Tera,
+    file: &Path,
+    source: &str,
+    body: &str,
+) -> anyhow::Result<shortcodes::Expanded> {
+    let first_line = source[..source.len() - body.len()].matches('\n').count() + 1;
+    let name = Path::new(CONTENT_DIR).join(file);
+    shortcodes::expand(tera, &name.to_string_lossy(), first_line, body)
+}
+
+/// Emoji and typography passes of the rendered `html` of a file, as its front
+/// matter (`emoji`, `lang`) and `config` say.
+fn postprocess(config: &Config, front: &FrontMatter, html: String) -> String {
+    let html = match front.emoji {
+        Some(false) => html,
+        _ => filters::replace_emoji(config, &html),
+    };
+    filters::typography(config, &html, front.seo.lang.as_deref())
+}
+
+/// Loads every markdown file under `dir`, grouped by section.
+/// Drafts, scheduled and expired pages are kept: it is up to the caller to publish
+/// them or not. Shortcodes are rendered with `tera`, markdown as `config` says.
+pub fn load(dir: &Path, tera: &Tera, config: &Config) -> anyhow::Result<Vec<Section>> {
+    let mut sections: BTreeMap<String, Section> = BTreeMap::new();
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    // Le contenu ignoré par git est publié quand même
+    for entry in ignore::WalkBuilder::new(dir)
+        .hidden(false)
+        .git_ignore(false)
+        .git_global(false)
+        .git_exclude(false)
+        .build()
+        .flatten()
+    {
+        let path = entry.path();
+        if !path.is_file() || path.extension().is_none_or(|e| e != "md") {
+            continue;
+        }
+        let file = path.strip_prefix(dir).unwrap_or(path).to_path_buf();
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        if file.file_name().is_some_and(|n| n == SECTION_INDEX) {
+            let name = dir_name(file.parent());
+            let (front, body) = FrontMatter::parse(&source)
+                .with_context(|| format!("invalid front matter in {}", file.display()))?;
+            let section = sections
+                .entry(name.clone())
+                .or_insert_with(|| Section::new(&name));
+            let expanded = expand(tera, &file, &source, body)?;
+            let html = filters::render_markdown_toc(config, &expanded.markdown, 1, 0).0;
+            section.content = expanded.restore(&postprocess(config, &front, html));
+            section.title = front.title;
+            section.description = front.description;
+            section.extra = front.extra;
+            section.seo = front.seo;
+            section.template = front.template;
+            section.paginate_by = front.paginate_by;
+            section.paginate_path = front.paginate_path;
+            section.has_index = true;
+            continue;
+        }
+
+        let page = Page::parse(&file, &source, tera, config)?;
+        sections
+            .entry(page.section.clone())
+            .or_insert_with(|| Section::new(&page.section))
+            .pages
+            .push(page);
+    }
+
+    let mut sections: Vec<Section> = sections.into_values().collect();
+    for section in &mut sections {
+        sort_pages(&mut section.pages);
+    }
+    Ok(sections)
+}
+
+/// Sorts pages newest first, undated pages last and by title.
+pub fn sort_pages(pages: &mut [Page]) {
+    pages.sort_by(|a, b| {
+        b.published()
+            .cmp(&a.published())
+            .then_with(|| a.title.cmp(&b.title))
+    });
+}
+
+/// Parses the dates accepted in front matter: RFC 3339, `YYYY-MM-DD HH:MM:SS`
+/// and `YYYY-MM-DD`.
+pub fn parse_date(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    parse_naive_date(s).map(|dt| dt.and_utc())
+}
+
+/// Date and time of `s` when it has no time zone: `2025-01-31`,
+/// `2025-01-31 08:30:00` or `2025-01-31T08:30:00`.
+pub fn parse_naive_date(s: &str) -> Option<NaiveDateTime> {
+    let s = s.trim();
+    for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(dt);
+        }
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+}
+
+/// Section of a page: its directory, or the parent one for `…/slug/index.md` bundles.
+fn section_of(file: &Path) -> String {
+    let parent = file.parent();
+    if file.file_stem().is_some_and(|s| s == "index") {
+        dir_name(parent.and_then(Path::parent))
+    } else {
+        dir_name(parent)
+    }
+}
+
+fn file_stem(file: &Path) -> &str {
+    let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    if stem == "index" {
+        file.parent()
+            .and_then(|p| p.file_name())
+            .and_then(|s| s.to_str())
+            .unwrap_or(stem)
+    } else {
+        stem
+    }
+}
+
+fn dir_name(dir: Option<&Path>) -> String {
+    dir.map(PathBuf::from)
+        .unwrap_or_default()
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_and_slugs_of_files() {
+        assert_eq!(section_of(Path::new("blog/hello.md")), "blog");
+        assert_eq!(section_of(Path::new("blog/hello/index.md")), "blog");
+        assert_eq!(section_of(Path::new("about.md")), "");
+        assert_eq!(file_stem(Path::new("blog/hello/index.md")), "hello");
+        assert_eq!(file_stem(Path::new("blog/hello.md")), "hello");
+    }
+
+    #[test]
+    fn front_matter_slugs_are_slugified() {
+        let config = Config::default();
+        let tera = Tera::default();
+        let parse = |source: &str| Page::parse(Path::new("blog/a.md"), source, &tera, &config);
+        let page = parse("+++\nslug = \"../../../escaped2\"\n+++\n").unwrap();
+        assert_eq!(
+            (page.slug.as_str(), page.path.as_str()),
+            ("escaped2", "/blog/escaped2/")
+        );
+        let page = parse("+++\nslug = \"Été/2024\"\n+++\n").unwrap();
+        assert_eq!(page.path, "/blog/ete-2024/");
+        let err = parse("+++\nslug = \"../..\"\n+++\n").unwrap_err();
+        assert_eq!(err.to_string(), "empty slug `../..` in blog/a.md");
+    }
+
+    #[test]
+    fn status_of_pages() {
+        let config = Config::default();
+        let tera = Tera::default();
+        let now = parse_date("2025-06-01T12:00:00Z").unwrap();
+        let status = |front: &str| {
+            let source = format!("+++\n{front}\n+++\n");
+            let page = Page::parse(Path::new("blog/a.md"), &source, &tera, &config).unwrap();
+            page.status(now)
+        };
+        assert_eq!(status(""), Status::Published);
+        assert_eq!(status("date = \"2025-06-01T12:00:00Z\""), Status::Published);
+        assert_eq!(status("date = \"2025-06-01T12:00:01Z\""), Status::Scheduled);
+        assert_eq!(status("date = \"2025-06-02\""), Status::Scheduled);
+        assert_eq!(status("expires = \"2025-06-01 12:00:00\""), Status::Expired);
+        assert_eq!(status("expires = \"2025-06-02\""), Status::Published);
+        assert_eq!(status("expires = \"soon\""), Status::Published);
+        // Un brouillon le reste, même expiré ; l'expiration passe avant la date
+        assert_eq!(
+            status("draft = true\nexpires = \"2020-01-01\""),
+            Status::Draft
+        );
+        assert_eq!(
+            status("date = \"2030-01-01\"\nexpires = \"2025-01-01\""),
+            Status::Expired
+        );
+    }
+
+    #[test]
+    fn publish_allows_what_it_is_asked_to() {
+        let all = [
+            Status::Published,
+            Status::Draft,
+            Status::Scheduled,
+            Status::Expired,
+        ];
+        let allowed = |publish: Publish| all.map(|s| publish.allows(s));
+        assert_eq!(allowed(Publish::default()), [true, false, false, false]);
+        let publish = Publish {
+            drafts: true,
+            future: true,
+        };
+        assert_eq!(allowed(publish), [true, true, true, false]);
+
+        let vars = |drafts: &'static str, future: Option<&'static str>| {
+            Publish::from_vars(move |k| match k {
+                DRAFTS_VAR => Some(drafts.to_string()),
+                _ => future.map(str::to_string),
+            })
+        };
+        let publish = vars("1", None);
+        assert!(publish.drafts && !publish.future);
+        let publish = vars("0", Some("yes"));
+        assert!(!publish.drafts && publish.future);
+        assert!(!vars("", Some("")).drafts);
+    }
+
+    #[test]
+    fn front_matter_dates() {
+        let utc = |s| parse_date(s).map(|d| d.to_rfc3339());
+        assert_eq!(
+            utc("2025-01-31").as_deref(),
+            Some("2025-01-31T00:00:00+00:00")
+        );
+        assert_eq!(
+            utc("2025-01-31 08:30:00").as_deref(),
+            Some("2025-01-31T08:30:00+00:00")
+        );
+        assert_eq!(
+            utc("2025-01-31T08:30:00+02:00").as_deref(),
+            Some("2025-01-31T06:30:00+00:00")
+        );
+        assert_eq!(utc("31/01/2025"), None);
+    }
+
+    #[test]
+    fn front_matter_can_turn_emoji_off_for_pages_and_sections() {
+        let dir = crate::testing::dir("content-emoji");
+        for (path, source) in [
+            ("blog/_index.md", "+++\nemoji = false\n+++\n:rocket:\n"),
+            ("blog/on.md", ":rocket:\n"),
+            ("blog/off.md", "---\nemoji: false\n---\n:rocket:\n"),
+            ("_index.md", ":rocket:\n"),
+        ] {
+            crate::testing::file(&dir, path, source);
+        }
+        let config = Config::default();
+        let tera = crate::render::engine(&dir.join("templates"), &config).unwrap();
+        let sections = load(&dir, &tera, &config).unwrap();
+        let content = |name: &str| {
+            let section = sections.iter().find(|s| s.name == name).unwrap();
+            section.content.trim().to_string()
+        };
+        assert_eq!(content(""), "<p>🚀</p>");
+        assert_eq!(content("blog"), "<p>:rocket:</p>");
+        let blog = sections.iter().find(|s| s.name == "blog").unwrap();
+        let page = |slug: &str| {
+            let page = blog.pages.iter().find(|p| p.slug == slug).unwrap();
+            page.content.trim().to_string()
+        };
+        assert_eq!(page("on"), "<p>🚀</p>");
+        assert_eq!(page("off"), "<p>:rocket:</p>");
+    }
+}
@@ -1,7 +1,42 @@
 use serde::{Deserialize, Serialize};
 
+pub mod config;
 pub mod console;
+pub mod content;
+pub mod site;
+
+/// Scratch directories of the tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::{fs, path::PathBuf};
+
+    /// An empty directory named after the test, under the system temp directory.
+    pub fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rey-{name}-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes `content` to `dir/path`, creating the parent directories.
+    pub fn file(dir: &std::path::Path, path: &str, content: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// The project made of `files` (path and content), loaded.
+    pub fn site(name: &str, files: &[(&str, &str)]) -> crate::site::Site {
+        let dir = dir(name);
+        for (path, content) in files {
+            file(&dir, path, content);
+        }
+        crate::site::Site::open(&dir, Default::default()).unwrap()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct Seo {
     /// Title (~60 chars)
     pub title: String,
@@ -13,6 +48,9 @@ pub struct Seo {
     pub canonical_url: Option<String>,
     pub lang: Option<String>,    // ex: "fr"
     pub updated: Option<String>, // ISO8601
+    /// `<meta name="robots" content="noindex">`, and left out of the sitemap; a page
+    /// setting `noindex = false` is indexed even when `[seo] noindex` is set.
+    pub noindex: Option<bool>,
 
     // Social
     pub og_image: Option<String>,
@@ -46,7 +84,11 @@ pub struct Seo {
 /// - `with_og_image(&mut self, img: &str)`: Sets the Open Graph image URL.
 /// - `with_og_type(&mut self, t: &str)`: Sets the Open Graph type (e.g., "website").
 /// - `with_twitter_card(&mut self, c: &str)`: Sets the Twitter card type.
+/// - `with_noindex(&mut self, n: bool)`: Keeps the page out of search engines and of the sitemap.
 /// - `twitter_summary(&mut self)`: Sets the Twitter card type to "summary".
+/// - `with_defaults(&mut self, d: &Seo)`: Fills the empty site-wide fields (author, lang, Open
+///   Graph image and type, Twitter card, noindex) from `d`.
+/// - `is_noindex(&self)`: Whether the page is kept out of search engines.
 ///
 /// All builder methods return a mutable reference to `Self`, allowing for method chaining.
 /// These methods ensure that SEO metadata can be set in a clear, concise, and type-safe manner,
@@ -62,6 +104,7 @@ impl Seo {
             canonical_url: None,
             lang: None,
             updated: None,
+            noindex: None,
             og_image: None,
             og_type: None,
             twitter_card: None,
@@ -127,10 +170,39 @@ impl Seo {
         self
     }
 
+    pub fn with_noindex(&mut self, n: bool) -> &mut Self {
+        self.noindex = Some(n);
+        self
+    }
+
     pub fn twitter_summary(&mut self) -> &mut Self {
         self.twitter_card.replace("summary".to_string());
         self
     }
+
+    pub fn with_defaults(&mut self, d: &Seo) -> &mut Self {
+        // Les champs propres à une page (canonical_url, updated, json_ld, slug…) ne
+        // s'héritent pas
+        for (field, default) in [
+            (&mut self.author, &d.author),
+            (&mut self.lang, &d.lang),
+            (&mut self.og_image, &d.og_image),
+            (&mut self.og_type, &d.og_type),
+            (&mut self.twitter_card, &d.twitter_card),
+        ] {
+            if field.is_none() {
+                field.clone_from(default);
+            }
+        }
+        if self.noindex.is_none() {
+            self.noindex = d.noindex;
+        }
+        self
+    }
+
+    pub fn is_noindex(&self) -> bool {
+        self.noindex.unwrap_or(false)
+    }
 }
 
 ///
@@ -140,14 +212,25 @@ impl Seo {
 /// and ensures that public assets are served correctly.
 ///     
 pub mod render {
+    use crate::config::{CONFIG_FILE, Config};
     use rocket::fairing::AdHoc;
-    use rocket_dyn_templates::tera::Tera;
+    use rocket_dyn_templates::tera::{self, Tera, Value};
     use rust_embed::RustEmbed;
+    use std::{collections::HashMap, fs, path::Path, sync::Arc};
 
+    pub mod emojis;
+    pub mod excerpt;
     pub mod filters;
     pub mod funcs;
     pub mod helpers;
+    pub mod highlight;
     pub mod jsonld;
+    pub mod locale;
+    pub mod numbers;
+    pub mod shortcodes;
+    pub mod toc;
+    pub mod typo;
+    pub mod words;
 
     #[derive(RustEmbed)]
     #[folder = "templates/"]
@@ -157,45 +240,152 @@ pub mod render {
     #[folder = "front/"]
     struct KitAssets;
 
-    pub fn attach() -> AdHoc {
-        AdHoc::on_ignite("roots_kit", |rocket| async {
-            let rocket = rocket.attach(rocket_dyn_templates::Template::custom(|engines| {
-                let tera: &mut Tera = &mut engines.tera;
-
-                // Charger les templates embarqués
-                for file in KitTemplates::iter() {
-                    let path = file.as_ref();
-                    if let Some(content) = KitTemplates::get(path) {
-                        let s = std::str::from_utf8(content.data.as_ref()).unwrap();
-                        tera.add_raw_template(path, s).expect(path);
-                    }
+    /// Registers the embedded kit templates, the filters and the functions on `tera`,
+    /// those depending on `rey.toml` reading `config`.
+    pub fn register(tera: &mut Tera, config: &Config) {
+        // Charger les templates embarqués
+        for file in KitTemplates::iter() {
+            let path = file.as_ref();
+            if let Some(content) = KitTemplates::get(path) {
+                let s = std::str::from_utf8(content.data.as_ref()).unwrap();
+                tera.add_raw_template(path, s).expect(path);
+            }
+        }
+
+        let config = Arc::new(config.clone());
+        tera.register_function("num_format", helpers::num_format);
+        tera.register_function("money_eur", helpers::money_eur);
+        tera.register_function("percent", function_with(&config, helpers::percent));
+        tera.register_function("number", function_with(&config, helpers::number));
+        tera.register_function("currency", function_with(&config, helpers::currency));
+        tera.register_function("safe_url", helpers::safe_url);
+        tera.register_function("external_rel", helpers::external_rel);
+        tera.register_function("csrf_input", helpers::csrf_input);
+        tera.register_function("active_link", helpers::active_link);
+        tera.register_function("paginate", helpers::paginate);
+
+        // Filtres
+        tera.register_filter("markdown", filter_with(&config, filters::markdown));
+        tera.register_filter("md_excerpt", filter_with(&config, filters::md_excerpt));
+        tera.register_filter("date", filter_with(&config, filters::date));
+        tera.register_filter("ago", filter_with(&config, filters::ago));
+        tera.register_filter("truncate", filters::truncate);
+        tera.register_filter("truncate_html", filters::truncate_html);
+        tera.register_filter("word_count", filters::word_count);
+        tera.register_filter("reading_time", filter_with(&config, filters::reading_time));
+        tera.register_filter("slugify", filters::slugify);
+        tera.register_filter("typo", filter_with(&config, filters::typo));
+        tera.register_filter("nl2br", filters::nl2br);
+        tera.register_filter("json", filters::json_pp);
+
+        // Fonctions
+        tera.register_function("asset", funcs::asset);
+        tera.register_function("asset_tag", funcs::asset_tag);
+        tera.register_function("img_srcset", funcs::img_srcset);
+        tera.register_function("picture", funcs::picture);
+        tera.register_function("canonical", function_with(&config, funcs::canonical));
+        tera.register_function("jsonld", jsonld::jsonld);
+        tera.register_function("seo_tags", funcs::seo_tags);
+        tera.register_function("html_lang", funcs::html_lang);
+        tera.register_function("feed_links", funcs::feed_links);
+        tera.register_function("markdown_toc", function_with(&config, toc::markdown_toc));
+    }
+
+    type ConfigFilter = fn(&Config, &Value, &HashMap<String, Value>) -> tera::Result<Value>;
+    type ConfigFunction = fn(&Config, &HashMap<String, Value>) -> tera::Result<Value>;
+
+    /// Tera filter calling `filter` with `config`.
+    fn filter_with(config: &Arc<Config>, filter: ConfigFilter) -> impl tera::Filter + use<> {
+        let config = Arc::clone(config);
+        move |value: &Value, args: &HashMap<String, Value>| filter(&config, value, args)
+    }
+
+    /// Tera function calling `function` with `config`.
+    fn function_with(
+        config: &Arc<Config>,
+        function: ConfigFunction,
+    ) -> impl tera::Function + use<> {
+        let config = Arc::clone(config);
+        move |args: &HashMap<String, Value>| function(&config, args)
+    }
+
+    /// Template suffixes autoescaped by `rocket_dyn_templates`, matched against the
+    /// path of the template file.
+    pub const AUTOESCAPE: &[&str] = &[
+        ".html.tera",
+        ".htm.tera",
+        ".xml.tera",
+        ".html",
+        ".htm",
+        ".xml",
+    ];
+
+    /// Builds a Tera instance outside of Rocket: same setup as [`attach`], with
+    /// `config`, plus every `*.tera` file found under `dir`, named like
+    /// `rocket_dyn_templates` does (`web/index.html.tera` → `web/index`) and
+    /// autoescaped the same way.
+    pub fn engine(dir: &Path, config: &Config) -> tera::Result<Tera> {
+        let mut tera = Tera::default();
+        tera.autoescape_on(AUTOESCAPE.to_vec());
+        register(&mut tera, config);
+        let mut templates = Vec::new();
+        if dir.is_dir() {
+            for entry in ignore::WalkBuilder::new(dir)
+                .hidden(false)
+                .build()
+                .flatten()
+            {
+                let path = entry.path();
+                if !path.is_file() || path.extension().is_none_or(|e| e != "tera") {
+                    continue;
                 }
+                templates.push((path.to_path_buf(), Some(template_name(dir, path))));
+            }
+        }
+        tera.add_template_files(templates)?;
+        Ok(tera)
+    }
+
+    /// Template name of `path` relative to the templates directory `dir`.
+    pub fn template_name(dir: &Path, path: &Path) -> String {
+        let rel = path.strip_prefix(dir).unwrap_or(path);
+        let name = rel.to_string_lossy().replace('\\', "/");
+        let name = name.trim_end_matches(".tera");
+        name.strip_suffix(".html").unwrap_or(name).to_string()
+    }
 
-                tera.register_function("num_format", helpers::num_format);
-                tera.register_function("money_eur", helpers::money_eur);
-                tera.register_function("percent", helpers::percent);
-                tera.register_function("safe_url", helpers::safe_url);
-                tera.register_function("external_rel", helpers::external_rel);
-                tera.register_function("csrf_input", helpers::csrf_input);
-                tera.register_function("active_link", helpers::active_link);
-                tera.register_function("paginate", helpers::paginate);
-
-                // Filtres
-                tera.register_filter("markdown", filters::markdown);
-                tera.register_filter("md_excerpt", filters::md_excerpt);
-                tera.register_filter("date", filters::date);
-                tera.register_filter("ago", filters::ago);
-                tera.register_filter("truncate", filters::truncate);
-                tera.register_filter("slugify", filters::slugify);
-                tera.register_filter("nl2br", filters::nl2br);
-                tera.register_filter("json", filters::json_pp);
-
-                // Fonctions
-                tera.register_function("asset", funcs::asset);
-                tera.register_function("asset_tag", funcs::asset_tag);
-                tera.register_function("img_srcset", funcs::img_srcset);
-                tera.register_function("picture", funcs::picture);
-                tera.register_function("canonical", funcs::canonical);
+    /// Writes the embedded kit assets (roots.css, icons.svg) into `out`.
+    pub fn extract_assets(out: &Path) -> std::io::Result<()> {
+        fs::create_dir_all(out)?;
+        for f in KitAssets::iter() {
+            let path = out.join(f.as_ref());
+            if let Some(content) = KitAssets::get(f.as_ref()) {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, content.data.as_ref())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `rey.toml` of the running app; `APP_PUBLIC_URL` sets the `[site] base_url` of
+    /// projects without one.
+    fn app_config() -> Config {
+        let mut config = Config::load().unwrap_or_default();
+        if !Path::new(CONFIG_FILE).exists()
+            && let Ok(url) = std::env::var("APP_PUBLIC_URL")
+        {
+            config.site.base_url = url;
+        }
+        config
+    }
+
+    pub fn attach() -> AdHoc {
+        AdHoc::on_ignite("roots_kit", |rocket| async {
+            let config = app_config();
+            let rocket = rocket.attach(rocket_dyn_templates::Template::custom(move |engines| {
+                register(&mut engines.tera, &config);
             }));
 
             // Exposer assets (roots.css, icons.svg)
@@ -208,20 +398,107 @@ pub mod render {
                 .attach(AdHoc::on_liftoff("extract_assets", |_| {
                     Box::pin(async {
                         // à la liftoff, on écrit les assets embarqués sur disque (ou servez via route custom)
-                        use std::{fs, path::Path};
-                        let out = "target/roots_kit_assets";
-                        fs::create_dir_all(out).ok();
-                        for f in KitAssets::iter() {
-                            let path = format!("{out}/{}", f.as_ref());
-                            if let Some(content) = KitAssets::get(f.as_ref()) {
-                                if let Some(parent) = Path::new(&path).parent() {
-                                    fs::create_dir_all(parent).ok();
-                                }
-                                fs::write(path, content.data.as_ref()).ok();
-                            }
-                        }
+                        extract_assets(Path::new("target/roots_kit_assets")).ok();
                     })
                 }))
         })
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testing;
+        use rocket_dyn_templates::tera::Context;
+
+        #[test]
+        fn engine_autoescapes_html_templates() {
+            let dir = testing::dir("engine-autoescape");
+            testing::file(&dir, "page.html.tera", "<h1>{{ page.title }}</h1>");
+            testing::file(&dir, "feed.xml.tera", "<title>{{ page.title }}</title>");
+            testing::file(&dir, "robots.txt.tera", "{{ page.title }}");
+            let tera = engine(&dir, &Config::default()).unwrap();
+            let mut ctx = Context::new();
+            ctx.insert(
+                "page",
+                &serde_json::json!({ "title": "<script>alert(1)</script>" }),
+            );
+            assert_eq!(
+                tera.render("page", &ctx).unwrap(),
+                "<h1>&lt;script&gt;alert(1)&lt;&#x2F;script&gt;</h1>"
+            );
+            assert!(
+                tera.render("feed.xml", &ctx)
+                    .unwrap()
+                    .contains("&lt;script&gt;")
+            );
+            assert_eq!(
+                tera.render("robots.txt", &ctx).unwrap(),
+                "<script>alert(1)</script>"
+            );
+        }
+
+        #[test]
+        fn filters_and_functions_read_the_given_config() {
+            let dir = testing::dir("engine-config");
+            testing::file(
+                &dir,
+                "page.html.tera",
+                "{{ canonical(path='/a/') | safe }} {{ ':wave:' | markdown | safe }}",
+            );
+            let mut config = Config::default();
+            config.site.base_url = "https://example.org/".to_string();
+            config.markdown.emoji = false;
+            let tera = engine(&dir, &config).unwrap();
+            assert_eq!(
+                tera.render("page", &Context::new()).unwrap().trim(),
+                "https://example.org/a/ <p>:wave:</p>"
+            );
+        }
+
+        #[test]
+        fn template_names_drop_html_and_tera() {
+            let dir = Path::new("templates");
+            let name = |p: &str| template_name(dir, &dir.join(p));
+            assert_eq!(name("web/index.html.tera"), "web/index");
+            assert_eq!(name("sitemap.xml.tera"), "sitemap.xml");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_site_wide_seo_fields_are_inherited() {
+        let mut site = Seo::new();
+        site.with_author("Ana")
+            .with_lang("fr")
+            .with_canonical("https://ex.com/")
+            .with_updated("2025-01-01")
+            .with_json_ld("{}")
+            .with_slug("home")
+            .with_content_type("work")
+            .with_noindex(true);
+        let mut page = Seo::new();
+        page.with_lang("en").with_defaults(&site);
+        assert_eq!(page.author.as_deref(), Some("Ana"));
+        assert_eq!(page.lang.as_deref(), Some("en"));
+        assert_eq!(page.canonical_url, None);
+        assert_eq!(page.updated, None);
+        assert_eq!(page.json_ld, None);
+        assert_eq!(page.slug, None);
+        assert_eq!(page.content_type, None);
+        assert!(page.is_noindex());
+    }
+
+    #[test]
+    fn pages_can_opt_back_into_indexing() {
+        let mut site = Seo::new();
+        site.with_noindex(true);
+        let mut page = Seo::new();
+        page.with_noindex(false).with_defaults(&site);
+        assert!(!page.is_noindex());
+        assert!(!Seo::new().with_defaults(&Seo::new()).is_noindex());
+    }
 }
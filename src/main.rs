@@ -1,6 +1,5 @@
-use clap::{Parser, command};
+use clap::Parser;
 use rey::console::{Commands, Shell};
-pub mod console;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -11,11 +10,14 @@ struct Cli {
 impl Shell for Cli {
     fn run(&self) {
         match &self.command {
+            Commands::Build(cmd) => cmd.run(),
             Commands::Clean(cmd) => cmd.run(),
             Commands::Edit(cmd) => cmd.run(),
+            Commands::Highlight(cmd) => cmd.run(),
             Commands::Init(cmd) => cmd.run(),
             Commands::Run(cmd) => cmd.run(),
             Commands::Scan(cmd) => cmd.run(),
+            Commands::Seo(cmd) => cmd.run(),
             Commands::Watch(cmd) => cmd.run(),
             Commands::Serve(cmd) => cmd.run(),
         }
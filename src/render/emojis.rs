@@ -0,0 +1,139 @@
+//! `:rocket:` emoji shortcodes of rendered markdown.
+//!
+//! Names are the Unicode (CLDR) ones, with `_` or `-` for spaces (`:thumbs_up:`,
+//! `:red-heart:`), plus a few GitHub aliases such as `:+1:` or `:tada:`. Unknown
+//! names are left as is, and so is the text of `<code>` and `<pre>`.
+
+use crate::render::helpers::escape;
+use emoji::Emoji;
+
+/// GitHub shortcodes that are not CLDR names.
+const ALIASES: &[(&str, &str)] = &[
+    ("+1", "thumbs up"),
+    ("-1", "thumbs down"),
+    ("thumbsup", "thumbs up"),
+    ("thumbsdown", "thumbs down"),
+    ("heart", "red heart"),
+    ("smile", "grinning face with smiling eyes"),
+    ("smiley", "grinning face with big eyes"),
+    ("laughing", "grinning squinting face"),
+    ("wink", "winking face"),
+    ("tada", "party popper"),
+    ("bulb", "light bulb"),
+    ("white_check_mark", "check mark button"),
+    ("x", "cross mark"),
+];
+
+/// Elements whose text is left alone.
+const RAW_ELEMENTS: &[&str] = &["code", "pre", "script", "style"];
+
+/// Emoji named by the shortcode `name` (without colons).
+pub fn lookup(name: &str) -> Option<&'static Emoji> {
+    if let Some((_, cldr)) = ALIASES.iter().find(|(alias, _)| *alias == name) {
+        return emoji::lookup_by_name::lookup(cldr);
+    }
+    let name = name.to_lowercase().replace('_', " ");
+    emoji::lookup_by_name::lookup(&name)
+        .or_else(|| emoji::lookup_by_name::lookup(&name.replace('-', " ")))
+}
+
+/// Replaces the emoji shortcodes of the text of `html`, each emoji wrapped in
+/// `<span role="img" aria-label="…">` with `aria`.
+pub fn replace(html: &str, aria: bool) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut raw = 0usize;
+    let mut rest = html;
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            let tag = &rest[..end];
+            let name: String = tag
+                .trim_start_matches(['<', '/'])
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase();
+            if RAW_ELEMENTS.contains(&name.as_str()) {
+                if tag.starts_with("</") {
+                    raw = raw.saturating_sub(1);
+                } else {
+                    raw += 1;
+                }
+            }
+            out.push_str(tag);
+            rest = &rest[end..];
+            continue;
+        }
+        let end = rest.find('<').unwrap_or(rest.len());
+        if raw == 0 {
+            replace_text(&rest[..end], aria, &mut out);
+        } else {
+            out.push_str(&rest[..end]);
+        }
+        rest = &rest[end..];
+    }
+    out
+}
+
+fn replace_text(text: &str, aria: bool, out: &mut String) {
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+')))
+            .unwrap_or(after.len());
+        let found = (len > 0 && after[len..].starts_with(':'))
+            .then(|| lookup(&after[..len]))
+            .flatten();
+        match found {
+            Some(emoji) => {
+                if aria {
+                    out.push_str(&format!(
+                        "<span role=\"img\" aria-label=\"{}\">{}</span>",
+                        escape(emoji.name),
+                        emoji.glyph
+                    ));
+                } else {
+                    out.push_str(emoji.glyph);
+                }
+                rest = &after[len + 1..];
+            }
+            // Le `:` fermant peut ouvrir le shortcode suivant
+            None => {
+                out.push(':');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcodes_are_replaced_outside_code() {
+        assert_eq!(
+            replace("<p>:rocket: :+1: :thumbs_up:</p>", false),
+            "<p>🚀 👍 👍</p>"
+        );
+        assert_eq!(
+            replace("<p>:tada:</p>", true),
+            "<p><span role=\"img\" aria-label=\"party popper\">🎉</span></p>"
+        );
+        let code = "<pre><code>:rocket: <span>:tada:</span></code></pre><code>:x:</code>";
+        assert_eq!(replace(code, false), code);
+        assert_eq!(
+            replace("<p>a</p><code>:x:</code> :x:", false),
+            "<p>a</p><code>:x:</code> ❌"
+        );
+    }
+
+    #[test]
+    fn unknown_names_and_times_are_kept() {
+        assert_eq!(replace("12:30:45 :nope: a:b", false), "12:30:45 :nope: a:b");
+        assert_eq!(replace("::rocket:", false), ":🚀");
+    }
+}
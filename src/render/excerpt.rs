@@ -0,0 +1,210 @@
+//! Excerpts and truncation shared by `page.summary`, the feeds and the
+//! `truncate`, `truncate_html` and `md_excerpt` filters.
+//!
+//! A summary is the markdown before `<!-- more -->`, or else the first words of the
+//! rendered page. Text is cut between grapheme clusters (`é` written as `e` plus a
+//! combining accent stays whole) and HTML is cut between words, its open tags
+//! being closed.
+
+use comrak::{Arena, ComrakOptions, nodes::NodeValue, parse_document};
+use unicode_segmentation::UnicodeSegmentation;
+
+pub const MORE_SEPARATOR: &str = "<!-- more -->";
+pub const ELLIPSIS: &str = "…";
+
+/// Elements without a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// The markdown before `<!-- more -->`, if `markdown` has one on a line of its own
+/// (not in a code block, say).
+pub fn before_more(markdown: &str) -> Option<&str> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, &ComrakOptions::default());
+    let line = root.children().find_map(|node| {
+        let data = node.data.borrow();
+        match &data.value {
+            NodeValue::HtmlBlock(html) if html.literal.trim() == MORE_SEPARATOR => {
+                Some(data.sourcepos.start.line)
+            }
+            _ => None,
+        }
+    })?;
+    // Début de la ligne du séparateur, comptée à partir de 1
+    let end = markdown
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum();
+    Some(&markdown[..end])
+}
+
+/// The first `n` grapheme clusters of `text`, followed by `…` when it is longer.
+pub fn truncate(text: &str, n: usize) -> String {
+    match text.grapheme_indices(true).nth(n) {
+        Some((end, _)) => format!("{}{ELLIPSIS}", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+/// The first `n` words of `text`, followed by `…` when it has more.
+pub fn words(text: &str, n: usize) -> String {
+    let mut words = text.split_whitespace();
+    let mut out = words.by_ref().take(n).collect::<Vec<_>>().join(" ");
+    if words.next().is_some() {
+        out.push_str(ELLIPSIS);
+    }
+    out
+}
+
+/// Elements that do not separate the words around them.
+pub(crate) const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "code", "del", "em", "i", "kbd", "mark", "s", "small", "span", "strong",
+    "sub", "sup", "u",
+];
+
+/// The first `n` words of the text of `html`, followed by `…` when it has more,
+/// with the tags left open at the cut closed.
+pub fn truncate_html(html: &str, n: usize) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut open: Vec<String> = Vec::new();
+    let mut count = 0;
+    // Longueur de la sortie et balises ouvertes à la fin du dernier mot
+    let mut cut: (usize, Vec<String>) = (0, Vec::new());
+    // Vrai quand le texte qui suit commence un nouveau mot
+    let mut boundary = true;
+    let mut rest = html;
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            let tag = &rest[..end];
+            let name = tag_name(tag);
+            if tag.starts_with("</") {
+                if let Some(i) = open.iter().rposition(|t| *t == name) {
+                    open.truncate(i);
+                }
+            } else if !tag.starts_with("<!")
+                && !tag.ends_with("/>")
+                && !VOID_ELEMENTS.contains(&name.as_str())
+            {
+                open.push(name.clone());
+            }
+            boundary |= !INLINE_ELEMENTS.contains(&name.as_str());
+            out.push_str(tag);
+            rest = &rest[end..];
+            continue;
+        }
+        let end = rest.find('<').unwrap_or(rest.len());
+        let text = &rest[..end];
+        // Fin du dernier mot du texte
+        let mut word_end = None;
+        for (i, c) in text.char_indices() {
+            let space = c.is_whitespace();
+            if !space && boundary {
+                if count == n {
+                    // On coupe après le mot n, avec les balises ouvertes à cet endroit
+                    let open = match word_end {
+                        Some(e) => {
+                            out.push_str(&text[..e]);
+                            open
+                        }
+                        None => {
+                            out.truncate(cut.0);
+                            cut.1
+                        }
+                    };
+                    out.push_str(ELLIPSIS);
+                    for tag in open.iter().rev() {
+                        out.push_str(&format!("</{tag}>"));
+                    }
+                    return out;
+                }
+                count += 1;
+            }
+            if !space {
+                word_end = Some(i + c.len_utf8());
+            }
+            boundary = space;
+        }
+        if let Some(e) = word_end {
+            cut = (out.len() + e, open.clone());
+        }
+        out.push_str(text);
+        rest = &rest[end..];
+    }
+    out
+}
+
+/// Lowercase name of an HTML `tag`, e.g. `em` for `</EM>`.
+pub(crate) fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches(['<', '/'])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_summary_stops_at_a_separator_of_its_own() {
+        assert_eq!(
+            before_more("Intro *one*.\n\n<!-- more -->\n\nRest.\n"),
+            Some("Intro *one*.\n\n")
+        );
+        assert_eq!(
+            before_more("Intro.\n<!-- more -->\nRest.\n"),
+            Some("Intro.\n")
+        );
+        let fenced = "Intro.\n\n```html\n<!-- more -->\n```\n\nRest.\n";
+        assert_eq!(before_more(fenced), None);
+        assert_eq!(before_more("    <!-- more -->\n"), None);
+        assert_eq!(before_more("No separator.\n"), None);
+    }
+
+    #[test]
+    fn text_is_cut_between_grapheme_clusters() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("exact", 5), "exact");
+        assert_eq!(truncate("two words", 4), "two…");
+        // « é » écrit e + accent combinant reste entier
+        assert_eq!(truncate("cafe\u{301}s", 4), "cafe\u{301}…");
+        assert_eq!(truncate("👩‍👩‍👧 family", 1), "👩‍👩‍👧…");
+    }
+
+    #[test]
+    fn words_are_counted_on_whitespace() {
+        assert_eq!(words("one  two\nthree", 3), "one two three");
+        assert_eq!(words("one two three", 2), "one two…");
+        assert_eq!(words("", 2), "");
+    }
+
+    #[test]
+    fn html_is_cut_between_words_and_closed() {
+        let html = "<p>One <em>two three</em> four</p>";
+        assert_eq!(truncate_html(html, 4), html);
+        assert_eq!(truncate_html(html, 2), "<p>One <em>two…</em></p>");
+        assert_eq!(truncate_html(html, 3), "<p>One <em>two three…</em></p>");
+        // Une balise en ligne ne sépare pas les mots, un bloc si
+        assert_eq!(
+            truncate_html("<p>a<b>b</b>c d</p>", 1),
+            "<p>a<b>b</b>c…</p>"
+        );
+        assert_eq!(truncate_html("<p>One</p><p>Two</p>", 1), "<p>One…</p>");
+        assert_eq!(
+            truncate_html("<p>One<br>two <img src=\"x\"> three</p>", 2),
+            "<p>One<br>two…</p>"
+        );
+        assert_eq!(
+            truncate_html(
+                "<ul><li>One <a href=\"/\">two</a></li><li>three</li></ul>",
+                2
+            ),
+            "<ul><li>One <a href=\"/\">two…</a></li></ul>"
+        );
+    }
+}
@@ -1,76 +1,267 @@
-use chrono::{DateTime, Utc};
-use comrak::{ComrakOptions, markdown_to_html};
+use crate::{
+    config::Config,
+    content::parse_naive_date,
+    render::{
+        emojis, excerpt,
+        highlight::Highlighter,
+        locale::Lang,
+        toc::{Headings, TocEntry},
+        typo,
+        words::Words,
+    },
+};
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use comrak::{
+    Arena, ComrakOptions, Plugins, markdown_to_html_with_plugins, nodes::NodeValue, parse_document,
+};
 use rocket_dyn_templates::tera;
 use rocket_dyn_templates::tera::Result as TeraResult;
 use rocket_dyn_templates::tera::Value;
 use rocket_dyn_templates::tera::to_value;
+use std::fmt::Write as _;
 
-pub fn markdown(value: &Value, _: &std::collections::HashMap<String, Value>) -> TeraResult<Value> {
-    let s = value.as_str().unwrap_or_default();
+/// Comrak options of `[markdown]` and `[highlight]`.
+fn options(config: &Config) -> ComrakOptions<'static> {
+    let md = &config.markdown;
     let mut opt = ComrakOptions::default();
-    opt.extension.table = true;
-    opt.extension.strikethrough = true;
-    let html = markdown_to_html(s, &opt);
-    Ok(to_value(html).unwrap())
+    opt.extension.table = md.table;
+    opt.extension.strikethrough = md.strikethrough;
+    opt.extension.footnotes = md.footnotes;
+    opt.extension.tasklist = md.tasklist;
+    opt.extension.autolink = md.autolink;
+    opt.extension.superscript = md.superscript;
+    opt.extension.description_lists = md.description_lists;
+    opt.extension.tagfilter = md.tagfilter;
+    opt.extension.front_matter_delimiter = md.front_matter_delimiter.clone();
+    opt.parse.smart = md.smart;
+    opt.render.hardbreaks = md.hardbreaks;
+    opt.render.unsafe_ = md.unsafe_html;
+    opt.render.escape = md.escape;
+    opt.render.github_pre_lang = md.github_pre_lang;
+    opt.render.figure_with_caption = md.figure_with_caption;
+    // Le surligneur lit les lignes marquées dans la suite de l'info string
+    opt.render.full_info_string = config.highlight.enabled;
+    opt
+}
+
+/// Markdown to HTML, shared by the `markdown` filter and the `content/` pages.
+pub fn render_markdown(config: &Config, s: &str) -> String {
+    typography(
+        config,
+        &replace_emoji(config, &render_markdown_toc(config, s, 1, 0).0),
+        None,
+    )
+}
+
+/// Typographic fixes of rendered `html` in `lang` (else `[seo] lang`), when
+/// `[markdown] typo` is on.
+pub fn typography(config: &Config, html: &str, lang: Option<&str>) -> String {
+    if config.markdown.typo {
+        typo::apply(html, content_lang(config, lang))
+    } else {
+        html.to_string()
+    }
+}
+
+/// Language of `lang`, else of `[seo] lang`.
+fn content_lang(config: &Config, lang: Option<&str>) -> Lang {
+    Lang::from_tag(lang.or(config.seo.lang.as_deref()).unwrap_or_default())
 }
 
+/// Emoji shortcodes of rendered `html` replaced, unless `[markdown] emoji` is off.
+pub fn replace_emoji(config: &Config, html: &str) -> String {
+    if config.markdown.emoji {
+        emojis::replace(html, config.markdown.emoji_aria)
+    } else {
+        html.to_string()
+    }
+}
+
+/// Markdown to HTML with heading IDs, and the table of contents of the headings
+/// from level `min` to `max`.
+pub fn render_markdown_toc(config: &Config, s: &str, min: u8, max: u8) -> (String, Vec<TocEntry>) {
+    let headings = Headings::new(config.markdown.anchors);
+    let highlighter = Highlighter::new(&config.highlight);
+    let mut plugins = Plugins::default();
+    plugins.render.heading_adapter = Some(&headings);
+    if config.highlight.enabled {
+        plugins.render.codefence_syntax_highlighter = Some(&highlighter);
+    }
+    let html = markdown_to_html_with_plugins(s, &options(config), &plugins);
+    (html, headings.toc(min, max))
+}
+
+pub fn markdown(
+    config: &Config,
+    value: &Value,
+    _: &std::collections::HashMap<String, Value>,
+) -> TeraResult<Value> {
+    let s = value.as_str().unwrap_or_default();
+    Ok(to_value(render_markdown(config, s)).unwrap())
+}
+
+/// `{{ body | md_excerpt(words=40) }}`: first words of the plain text of the
+/// markdown before `<!-- more -->` (or of all of it), parsed with the `[markdown]`
+/// options.
 pub fn md_excerpt(
+    config: &Config,
     value: &Value,
     args: &std::collections::HashMap<String, Value>,
 ) -> TeraResult<Value> {
     let words = args.get("words").and_then(|v| v.as_u64()).unwrap_or(40) as usize;
     let s = value.as_str().unwrap_or_default();
-    let text = s
-        .lines()
-        .find(|l| !l.trim().is_empty())
-        .unwrap_or("")
-        .to_string();
-    let out = text
-        .split_whitespace()
-        .take(words)
-        .collect::<Vec<_>>()
-        .join(" ");
-    Ok(to_value(out).unwrap())
+    let s = excerpt::before_more(s).unwrap_or(s);
+    let arena = Arena::new();
+    let root = parse_document(&arena, s, &options(config));
+    let mut text = String::new();
+    plain_text(root, &mut text);
+    Ok(to_value(excerpt::words(&text, words)).unwrap())
 }
 
-pub fn date(value: &Value, args: &std::collections::HashMap<String, Value>) -> TeraResult<Value> {
+/// Text of the prose of `node`, its code blocks and headings left out.
+fn plain_text<'a>(node: &'a comrak::nodes::AstNode<'a>, text: &mut String) {
+    match &node.data.borrow().value {
+        NodeValue::CodeBlock(_) | NodeValue::HtmlBlock(_) | NodeValue::Heading(_) => return,
+        NodeValue::Text(t) => text.push_str(t),
+        NodeValue::Code(c) => text.push_str(&c.literal),
+        // Les blocs et les sauts de ligne séparent les mots
+        NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+        value if value.block() => text.push(' '),
+        _ => {}
+    }
+    for child in node.children() {
+        plain_text(child, text);
+    }
+}
+
+/// `{{ page.date | date(fmt="%e %B %Y", lang=page.seo.lang, tz="Europe/Paris") }}`:
+/// a date (string or Unix timestamp) formatted with the month and day names of
+/// `lang`, in the time zone `tz`. Both default to `[seo] lang` and
+/// `[site] timezone`: a filter does not see the page, so a page in another
+/// language has to pass its own `lang`.
+pub fn date(
+    config: &Config,
+    value: &Value,
+    args: &std::collections::HashMap<String, Value>,
+) -> TeraResult<Value> {
     let fmt = args
         .get("fmt")
         .and_then(|v| v.as_str())
         .unwrap_or("%Y-%m-%d");
-    let s = value.as_str().unwrap_or_default();
-    let dt: DateTime<Utc> = s.parse().unwrap_or_else(|_| Utc::now());
-    Ok(to_value(dt.format(fmt).to_string()).unwrap())
+    let tz = timezone(config, args)?;
+    let dt = parse_value(value, tz)?;
+    let fmt = lang(config, args).localize_format(fmt, &dt);
+    let mut out = String::new();
+    write!(out, "{}", dt.format(&fmt))
+        .map_err(|_| tera::Error::msg(format!("date: invalid format `{fmt}`")))?;
+    Ok(to_value(out).unwrap())
 }
 
-pub fn ago(value: &Value, _args: &std::collections::HashMap<String, Value>) -> TeraResult<Value> {
-    let s = value.as_str().unwrap_or_default();
-    let then: DateTime<Utc> = s.parse().unwrap_or_else(|_| Utc::now());
-    let sec = (Utc::now() - then).num_seconds();
-    let human = if sec < 60 {
-        format!("il y a {}s", sec)
-    } else if sec < 3600 {
-        format!("il y a {}min", sec / 60)
-    } else if sec < 86400 {
-        format!("il y a {}h", sec / 3600)
-    } else {
-        format!("il y a {}j", sec / 86400)
+/// `{{ page.date | ago(lang=page.seo.lang) }}`: time from now, e.g. "il y a 3
+/// jours" or "in 2 hours", `lang` defaulting to `[seo] lang`, not to the page's.
+pub fn ago(
+    config: &Config,
+    value: &Value,
+    args: &std::collections::HashMap<String, Value>,
+) -> TeraResult<Value> {
+    let then = parse_value(value, timezone(config, args)?)?;
+    let delta = Utc::now() - then.with_timezone(&Utc);
+    Ok(to_value(lang(config, args).relative(delta)).unwrap())
+}
+
+/// Language of the `lang` argument, else of `[seo] lang`: Tera filters get no
+/// template context, so templates pass `lang=page.seo.lang` themselves.
+fn lang(config: &Config, args: &std::collections::HashMap<String, Value>) -> Lang {
+    content_lang(config, args.get("lang").and_then(|v| v.as_str()))
+}
+
+/// Time zone of the `tz` argument, else of `[site] timezone`, else UTC.
+fn timezone(config: &Config, args: &std::collections::HashMap<String, Value>) -> TeraResult<Tz> {
+    let name = args
+        .get("tz")
+        .and_then(|v| v.as_str())
+        .or(config.site.timezone.as_deref());
+    match name {
+        Some(name) => name
+            .parse()
+            .map_err(|_| tera::Error::msg(format!("unknown time zone `{name}`"))),
+        None => Ok(Tz::UTC),
+    }
+}
+
+/// Date of a filtered value: a Unix timestamp, an RFC 3339 date converted to `tz`,
+/// or a date without time zone, taken as a local time of `tz`.
+fn parse_value(value: &Value, tz: Tz) -> TeraResult<DateTime<Tz>> {
+    let parsed = match value {
+        Value::Number(n) => n
+            .as_i64()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|dt| dt.with_timezone(&tz)),
+        Value::String(s) => DateTime::parse_from_rfc3339(s.trim())
+            .map(|dt| dt.with_timezone(&tz))
+            .ok()
+            .or_else(|| parse_naive_date(s).and_then(|dt| tz.from_local_datetime(&dt).earliest())),
+        _ => None,
     };
-    Ok(to_value(human).unwrap())
+    parsed.ok_or_else(|| tera::Error::msg(format!("cannot parse {value} as a date")))
 }
 
+/// `{{ title | truncate(n=60) }}`: the first `n` characters (grapheme clusters).
 pub fn truncate(
     value: &Value,
     args: &std::collections::HashMap<String, Value>,
 ) -> TeraResult<Value> {
     let n = args.get("n").and_then(|v| v.as_u64()).unwrap_or(160) as usize;
     let s = value.as_str().unwrap_or_default();
-    let out = if s.len() > n {
-        format!("{}…", &s[..n])
-    } else {
-        s.to_string()
-    };
-    Ok(to_value(out).unwrap())
+    Ok(to_value(excerpt::truncate(s, n)).unwrap())
+}
+
+/// `{{ page.content | truncate_html(words=40) | safe }}`: the first words of an
+/// HTML fragment, its open tags closed.
+pub fn truncate_html(
+    value: &Value,
+    args: &std::collections::HashMap<String, Value>,
+) -> TeraResult<Value> {
+    let words = args.get("words").and_then(|v| v.as_u64()).unwrap_or(40) as usize;
+    let s = value.as_str().unwrap_or_default();
+    Ok(to_value(excerpt::truncate_html(s, words)).unwrap())
+}
+
+/// `{{ page.content | word_count }}`: words of an HTML or plain text, code blocks
+/// excluded.
+pub fn word_count(
+    value: &Value,
+    _args: &std::collections::HashMap<String, Value>,
+) -> TeraResult<Value> {
+    let s = value.as_str().unwrap_or_default();
+    Ok(to_value(Words::of_html(s).total()).unwrap())
+}
+
+/// `{{ page.content | reading_time(wpm=250) }}`: minutes to read an HTML or plain
+/// text, at `[markdown] words_per_minute` by default.
+pub fn reading_time(
+    config: &Config,
+    value: &Value,
+    args: &std::collections::HashMap<String, Value>,
+) -> TeraResult<Value> {
+    let s = value.as_str().unwrap_or_default();
+    let mut markdown = config.markdown.clone();
+    if let Some(wpm) = args.get("wpm").and_then(|v| v.as_u64()) {
+        markdown.words_per_minute = wpm as usize;
+    }
+    Ok(to_value(Words::of_html(s).reading_time(&markdown)).unwrap())
+}
+
+/// `{{ text | typo(lang=page.seo.lang) | safe }}`: typographic fixes of an HTML
+/// fragment, `lang` defaulting to `[seo] lang`, not to the page's.
+pub fn typo(
+    config: &Config,
+    value: &Value,
+    args: &std::collections::HashMap<String, Value>,
+) -> TeraResult<Value> {
+    let s = value.as_str().unwrap_or_default();
+    Ok(to_value(typo::apply(s, lang(config, args))).unwrap())
 }
 
 pub fn slugify(
@@ -92,3 +283,85 @@ pub fn json_pp(
 ) -> TeraResult<Value> {
     Ok(Value::String(serde_json::to_string_pretty(value).unwrap()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn markdown_section_sets_the_comrak_options() {
+        let config = config(
+            "[markdown]\ntable = false\nfootnotes = true\nsmart = true\nunsafe = true\nfront_matter_delimiter = \"---\"\n",
+        );
+        let opt = options(&config);
+        assert!(!opt.extension.table);
+        assert!(opt.extension.strikethrough);
+        assert!(opt.extension.footnotes);
+        assert!(opt.parse.smart);
+        assert!(opt.render.unsafe_);
+        assert_eq!(opt.extension.front_matter_delimiter.as_deref(), Some("---"));
+        assert_eq!(opt.render.full_info_string, config.highlight.enabled);
+    }
+
+    #[test]
+    fn markdown_options_change_the_rendering() {
+        let source = "~~gone~~ <b>x</b>\n\n| a |\n|---|\n| 1 |\n";
+        let html = render_markdown(&Config::default(), source);
+        assert!(html.contains("<del>gone</del>"), "{html}");
+        assert!(html.contains("<table>"), "{html}");
+        assert!(!html.contains("<b>x</b>"), "{html}");
+
+        let config = config("[markdown]\nstrikethrough = false\ntable = false\nunsafe = true\n");
+        let html = render_markdown(&config, source);
+        assert!(html.contains("~~gone~~"), "{html}");
+        assert!(!html.contains("<table>"), "{html}");
+        assert!(html.contains("<b>x</b>"), "{html}");
+    }
+
+    #[test]
+    fn md_excerpt_keeps_the_prose_before_the_separator() {
+        let config = Config::default();
+        let excerpt = |md: &str, words: u64| {
+            let args = [("words".to_string(), to_value(words).unwrap())].into();
+            md_excerpt(&config, &to_value(md).unwrap(), &args).unwrap()
+        };
+        let md = "# Title\n\nSome *text* with `code`.\n\n```rust\nfn main() {}\n```\n\n<div>html</div>\n\nMore\ntext.\n";
+        assert_eq!(excerpt(md, 40), "Some text with code. More text.");
+        assert_eq!(excerpt(md, 3), "Some text with…");
+        assert_eq!(excerpt("One.\n\n<!-- more -->\n\nTwo.\n", 40), "One.");
+    }
+
+    #[test]
+    fn reading_time_defaults_to_the_markdown_section() {
+        let config = config("[markdown]\nwords_per_minute = 2\n");
+        let minutes = |wpm: Option<u64>| {
+            let args = wpm
+                .map(|w| ("wpm".to_string(), to_value(w).unwrap()))
+                .into_iter()
+                .collect();
+            let html = to_value("<p>one two three</p><pre>not read</pre>").unwrap();
+            reading_time(&config, &html, &args).unwrap()
+        };
+        assert_eq!(minutes(None), 2);
+        assert_eq!(minutes(Some(3)), 1);
+    }
+
+    #[test]
+    fn date_lang_defaults_to_the_seo_section() {
+        let config = config("[seo]\nlang = \"fr\"\n");
+        let date = |lang: Option<&str>| {
+            let mut args = std::collections::HashMap::new();
+            args.insert("fmt".to_string(), to_value("%e %B %Y").unwrap());
+            if let Some(lang) = lang {
+                args.insert("lang".to_string(), to_value(lang).unwrap());
+            }
+            date(&config, &to_value("2024-08-01").unwrap(), &args).unwrap()
+        };
+        assert_eq!(date(None), " 1 août 2024");
+        assert_eq!(date(Some("en-US")), " 1 August 2024");
+    }
+}
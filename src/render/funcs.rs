@@ -1,3 +1,8 @@
+use crate::{
+    Seo,
+    config::{Config, SiteConfig},
+    render::{helpers::escape, jsonld},
+};
 use once_cell::sync::Lazy;
 use rocket_dyn_templates::tera;
 use std::{collections::HashMap, fs};
@@ -61,8 +66,293 @@ pub fn picture(_args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value
     Ok(tera::to_value("").unwrap())
 }
 
-pub fn canonical(args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
-    let base = std::env::var("APP_PUBLIC_URL").unwrap_or_else(|_| "http://localhost:8000".into());
+/// `{{ canonical(path="/blog/") }}` : absolute URL of a site path.
+pub fn canonical(
+    config: &Config,
+    args: &HashMap<String, tera::Value>,
+) -> tera::Result<tera::Value> {
     let p = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
-    Ok(tera::to_value(format!("{base}{p}")).unwrap())
+    Ok(tera::to_value(config.url(p)).unwrap())
+}
+
+/// BCP 47 tag usable in `<html lang="…">`: `fr_FR` → `fr-FR`, `en` if unset.
+pub fn lang_tag(lang: Option<&str>) -> String {
+    match lang.map(str::trim) {
+        Some(l) if !l.is_empty() => l.replace('_', "-"),
+        _ => "en".to_string(),
+    }
+}
+
+/// Open Graph locale (`language_TERRITORY`): `fr` → `fr_FR`, `en-gb` → `en_GB`.
+pub fn og_locale(lang: &str) -> String {
+    let tag = lang_tag(Some(lang));
+    let (language, region) = tag.split_once('-').unwrap_or((&tag, ""));
+    let language = language.to_lowercase();
+    let region = match region {
+        "" => match language.as_str() {
+            "en" => "US".to_string(),
+            "ja" => "JP".to_string(),
+            "zh" => "CN".to_string(),
+            "ko" => "KR".to_string(),
+            "sv" => "SE".to_string(),
+            "da" => "DK".to_string(),
+            "el" => "GR".to_string(),
+            "cs" => "CZ".to_string(),
+            "uk" => "UA".to_string(),
+            other => other.to_uppercase(),
+        },
+        r => r.to_uppercase(),
+    };
+    format!("{language}_{region}")
+}
+
+/// `{{ html_lang(seo=seo) }}` : value for the `lang` attribute of `<html>`.
+pub fn html_lang(args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+    let lang = args
+        .get("seo")
+        .and_then(|v| v.get("lang"))
+        .or_else(|| args.get("lang"))
+        .and_then(|v| v.as_str());
+    Ok(tera::to_value(lang_tag(lang)).unwrap())
+}
+
+/// `{{ seo_tags(seo=seo, site=site) | safe }}` : the `<head>` meta tags of a page
+/// (title, description, canonical, Open Graph, Twitter card), all values escaped.
+pub fn seo_tags(args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+    let seo: Seo = match args.get("seo") {
+        Some(v) => serde_json::from_value(v.clone())
+            .map_err(|e| tera::Error::msg(format!("seo_tags: invalid `seo` argument: {e}")))?,
+        None => return Err(tera::Error::msg("seo_tags: missing `seo` argument")),
+    };
+    let site: Option<SiteConfig> = args
+        .get("site")
+        .and_then(|v| serde_json::from_value(v.clone()).ok());
+    Ok(tera::to_value(meta_tags(&seo, site.as_ref())).unwrap())
+}
+
+/// Renders the meta tags of [`seo_tags`].
+pub fn meta_tags(seo: &Seo, site: Option<&SiteConfig>) -> String {
+    let root = jsonld::site_url(seo, site);
+    let e = escape;
+    let meta =
+        |name: &str, content: &str| format!(r#"<meta name="{name}" content="{}">"#, e(content));
+    let prop =
+        |name: &str, content: &str| format!(r#"<meta property="{name}" content="{}">"#, e(content));
+
+    let mut tags = vec![format!("<title>{}</title>", e(&seo.title))];
+    if !seo.description.is_empty() {
+        tags.push(meta("description", &seo.description));
+    }
+    if !seo.keywords.is_empty() {
+        tags.push(meta("keywords", &seo.keywords.join(", ")));
+    }
+    if let Some(author) = &seo.author {
+        tags.push(meta("author", author));
+    }
+    if seo.is_noindex() {
+        tags.push(meta("robots", "noindex"));
+    }
+    if let Some(url) = &seo.canonical_url {
+        tags.push(format!(r#"<link rel="canonical" href="{}">"#, e(url)));
+    }
+
+    // Open Graph
+    let og_type = seo.og_type.as_deref().unwrap_or("website");
+    tags.push(prop("og:type", og_type));
+    tags.push(prop("og:title", &seo.title));
+    if !seo.description.is_empty() {
+        tags.push(prop("og:description", &seo.description));
+    }
+    if let Some(url) = &seo.canonical_url {
+        tags.push(prop("og:url", url));
+    }
+    if let Some(site) = site.filter(|s| !s.title.is_empty()) {
+        tags.push(prop("og:site_name", &site.title));
+    }
+    let image = seo.og_image.as_deref().map(|i| jsonld::absolute(&root, i));
+    if let Some(image) = &image {
+        tags.push(prop("og:image", image));
+    }
+    if let Some(lang) = &seo.lang {
+        tags.push(prop("og:locale", &og_locale(lang)));
+    }
+    if let Some(updated) = &seo.updated {
+        if og_type == "article" {
+            tags.push(prop("article:modified_time", updated));
+        } else {
+            tags.push(prop("og:updated_time", updated));
+        }
+    }
+    if og_type == "article" {
+        for keyword in &seo.keywords {
+            tags.push(prop("article:tag", keyword));
+        }
+    }
+
+    // Twitter
+    let card = seo.twitter_card.as_deref().unwrap_or(if image.is_some() {
+        "summary_large_image"
+    } else {
+        "summary"
+    });
+    tags.push(meta("twitter:card", card));
+    tags.push(meta("twitter:title", &seo.title));
+    if !seo.description.is_empty() {
+        tags.push(meta("twitter:description", &seo.description));
+    }
+    if let Some(image) = &image {
+        tags.push(meta("twitter:image", image));
+    }
+    tags.join("\n")
+}
+
+/// `{{ feed_links(feeds=feeds) | safe }}` : `<link rel="alternate">` tags of the feeds
+/// exposed to the template.
+pub fn feed_links(args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+    let feeds = args
+        .get("feeds")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let tags = feeds
+        .iter()
+        .map(|f| {
+            let get = |k: &str| f.get(k).and_then(|v| v.as_str()).unwrap_or_default();
+            format!(
+                r#"<link rel="alternate" type="{}" title="{}" href="{}">"#,
+                escape(get("type")),
+                escape(get("title")),
+                escape(get("href"))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(tera::to_value(tags).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seo() -> Seo {
+        let mut seo = Seo::new();
+        seo.with_title(r#"<b>"Rust" & 'Rey'</b>"#)
+            .with_desc("Less < more & \"quoted\"")
+            .with_canonical("https://example.com/a/?x=1&y=2");
+        seo
+    }
+
+    fn site(base_url: &str) -> SiteConfig {
+        SiteConfig {
+            title: "Rey & co".to_string(),
+            base_url: base_url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn meta_values_are_escaped() {
+        let tags = meta_tags(&seo(), Some(&site("https://example.com")));
+        let title = "&lt;b&gt;&quot;Rust&quot; &amp; &#39;Rey&#39;&lt;/b&gt;";
+        let description = "Less &lt; more &amp; &quot;quoted&quot;";
+        for expected in [
+            format!("<title>{title}</title>"),
+            format!(r#"<meta name="description" content="{description}">"#),
+            format!(r#"<meta property="og:title" content="{title}">"#),
+            format!(r#"<meta property="og:description" content="{description}">"#),
+            format!(r#"<meta name="twitter:title" content="{title}">"#),
+            format!(r#"<meta name="twitter:description" content="{description}">"#),
+            r#"<meta property="og:site_name" content="Rey &amp; co">"#.to_string(),
+        ] {
+            assert!(tags.contains(&expected), "{expected}\n{tags}");
+        }
+        assert!(
+            !tags.contains("<b>") && !tags.contains("\"Rust\""),
+            "{tags}"
+        );
+    }
+
+    #[test]
+    fn canonical_and_og_url() {
+        let tags = meta_tags(&seo(), None);
+        let url = "https://example.com/a/?x=1&amp;y=2";
+        assert!(tags.contains(&format!(r#"<link rel="canonical" href="{url}">"#)));
+        assert!(tags.contains(&format!(r#"<meta property="og:url" content="{url}">"#)));
+
+        let tags = meta_tags(&Seo::new(), None);
+        assert!(
+            !tags.contains("canonical") && !tags.contains("og:url"),
+            "{tags}"
+        );
+    }
+
+    #[test]
+    fn open_graph_and_twitter_fallbacks() {
+        // Sans image : type website, carte summary
+        let tags = meta_tags(&seo(), None);
+        assert!(tags.contains(r#"<meta property="og:type" content="website">"#));
+        assert!(tags.contains(r#"<meta name="twitter:card" content="summary">"#));
+        assert!(!tags.contains("og:image") && !tags.contains("twitter:image"));
+
+        // Une image relative est rendue absolue, d'après base_url sinon la canonique
+        let mut page = seo();
+        page.with_og_image("/img/cover.png");
+        let tags = meta_tags(&page, Some(&site("https://example.com/blog/")));
+        let image = "https://example.com/blog/img/cover.png";
+        assert!(tags.contains(&format!(r#"<meta property="og:image" content="{image}">"#)));
+        assert!(tags.contains(&format!(r#"<meta name="twitter:image" content="{image}">"#)));
+        assert!(tags.contains(r#"<meta name="twitter:card" content="summary_large_image">"#));
+        let tags = meta_tags(&page, None);
+        assert!(
+            tags.contains(r#"content="https://example.com/img/cover.png""#),
+            "{tags}"
+        );
+        page.with_og_image("https://cdn.example.net/c.png")
+            .with_twitter_card("summary");
+        let tags = meta_tags(&page, None);
+        assert!(
+            tags.contains(r#"<meta property="og:image" content="https://cdn.example.net/c.png">"#)
+        );
+        assert!(tags.contains(r#"<meta name="twitter:card" content="summary">"#));
+
+        // Une page sans image prend celle de [seo]
+        let mut defaults = Seo::new();
+        defaults.with_og_image("/default.png");
+        let mut page = seo();
+        page.with_defaults(&defaults);
+        let tags = meta_tags(&page, Some(&site("https://example.com")));
+        assert!(
+            tags.contains(
+                r#"<meta property="og:image" content="https://example.com/default.png">"#
+            )
+        );
+    }
+
+    #[test]
+    fn seo_tags_reads_its_arguments() {
+        let args: HashMap<String, tera::Value> = [
+            ("seo".to_string(), tera::to_value(seo()).unwrap()),
+            (
+                "site".to_string(),
+                tera::to_value(site("https://example.com")).unwrap(),
+            ),
+        ]
+        .into();
+        let tags = seo_tags(&args).unwrap();
+        assert!(tags.as_str().unwrap().contains("og:site_name"));
+        let err = seo_tags(&HashMap::new()).unwrap_err().to_string();
+        assert_eq!(err, "seo_tags: missing `seo` argument");
+    }
+
+    #[test]
+    fn feed_links_are_escaped() {
+        let feeds = serde_json::json!([
+            { "type": "application/rss+xml", "title": "Rey & \"co\"", "href": "https://example.com/rss.xml?a=1&b=2" },
+        ]);
+        let args = [("feeds".to_string(), feeds)].into();
+        assert_eq!(
+            feed_links(&args).unwrap(),
+            r#"<link rel="alternate" type="application/rss+xml" title="Rey &amp; &quot;co&quot;" href="https://example.com/rss.xml?a=1&amp;b=2">"#
+        );
+    }
 }
@@ -1,56 +1,102 @@
 // helpers.rs
+use crate::{config::Config, render::numbers::Locale};
 use rocket_dyn_templates::tera;
+use serde::Serialize;
 use serde_json::{Value, to_value};
 use std::collections::HashMap;
 
+/// Escapes `& < > " '` for HTML text and attribute values.
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `{{ number(n=1234.5, locale="fr-FR", decimals=2) }}` → `1 234,50`; without
+/// `decimals`, up to 3 of them are kept.
+pub fn number(config: &Config, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let n = arg_number(args, "number")?;
+    Ok(to_value(locale(config, args).number(n, decimals(args))).unwrap())
+}
+
+/// `{{ currency(n=-12, currency="USD", locale="en-US") }}` → `-$12.00`, `currency`
+/// defaulting to `EUR` and `decimals` to those of the currency.
+pub fn currency(config: &Config, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let n = arg_number(args, "currency")?;
+    let code = args
+        .get("currency")
+        .and_then(|v| v.as_str())
+        .unwrap_or("EUR");
+    Ok(to_value(locale(config, args).currency(n, code, decimals(args))).unwrap())
+}
+
+/// `{{ percent(n=0.125, decimals=1, locale="fr-FR") }}` → `12,5 %`, `locale`
+/// defaulting to `[seo] lang` like `number`, and a missing `n` being 0.
+pub fn percent(config: &Config, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let n = legacy_number(args);
+    let decimals = decimals(args).unwrap_or(0);
+    Ok(to_value(locale(config, args).percent(n, decimals)).unwrap())
+}
+
+/// `{{ num_format(n=1234) }}` → `1 234`: a whole number, French style, grouped with
+/// regular spaces as it always was (`number` uses the narrow no-break ones).
 pub fn num_format(args: &HashMap<String, Value>) -> tera::Result<Value> {
-    let n = args.get("n").and_then(|v| v.as_f64()).unwrap_or(0.0);
-    let s = format!("{:>.*}", 0, n); // entier
-    let with_spaces = s
-        .chars()
-        .rev()
-        .enumerate()
-        .map(|(i, c)| {
-            if i != 0 && i % 3 == 0 {
-                format!(" {c}")
-            } else {
-                c.to_string()
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("")
-        .chars()
-        .rev()
-        .collect::<String>();
-    Ok(Value::from(with_spaces))
+    let n = legacy_number(args);
+    Ok(to_value(plain_spaces(Locale::FrFr.number(n, Some(0)))).unwrap())
 }
 
+/// `{{ money_eur(n=1234.5) }}` → `1 234,50 €`: euros, French style, with regular
+/// spaces as `num_format`.
 pub fn money_eur(args: &HashMap<String, Value>) -> tera::Result<Value> {
-    let n = args.get("n").and_then(|v| v.as_f64()).unwrap_or(0.0);
-    let s = format!("{:.2}", n);
-    let (intp, decp) = s.split_once('.').unwrap_or((s.as_str(), "00"));
-    let spaced = intp
-        .chars()
-        .rev()
-        .enumerate()
-        .map(|(i, c)| {
-            if i != 0 && i % 3 == 0 {
-                format!(" {}", c)
-            } else {
-                c.to_string()
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("")
-        .chars()
-        .rev()
-        .collect::<String>();
-    Ok(to_value(format!("{spaced},{decp} €")).unwrap())
-}
-
-pub fn percent(args: &HashMap<String, Value>) -> tera::Result<Value> {
-    let n = args.get("n").and_then(|v| v.as_f64()).unwrap_or(0.0);
-    Ok(to_value(format!("{:.0}%", n * 100.0)).unwrap())
+    let n = legacy_number(args);
+    Ok(to_value(plain_spaces(Locale::FrFr.currency(n, "EUR", None))).unwrap())
+}
+
+/// Replaces the no-break spaces of a formatted number by regular ones.
+fn plain_spaces(s: String) -> String {
+    s.replace(['\u{a0}', '\u{202f}'], " ")
+}
+
+/// The `n` argument, as a number or a numeric string.
+fn arg_number(args: &HashMap<String, Value>, helper: &str) -> tera::Result<f64> {
+    let n = match args.get("n") {
+        Some(Value::Number(n)) => n.as_f64(),
+        Some(Value::String(s)) => s.trim().parse().ok(),
+        _ => None,
+    };
+    n.filter(|n| n.is_finite())
+        .ok_or_else(|| tera::Error::msg(format!("{helper}: `n` must be a number")))
+}
+
+/// The `n` argument of the helpers older than `number`, 0 when it is missing or not
+/// a number, as they always did.
+fn legacy_number(args: &HashMap<String, Value>) -> f64 {
+    arg_number(args, "").unwrap_or(0.0)
+}
+
+/// Locale of the `locale` argument, else of `[seo] lang`.
+fn locale(config: &Config, args: &HashMap<String, Value>) -> Locale {
+    let tag = args
+        .get("locale")
+        .and_then(|v| v.as_str())
+        .or(config.seo.lang.as_deref())
+        .unwrap_or_default();
+    Locale::from_tag(tag)
+}
+
+fn decimals(args: &HashMap<String, Value>) -> Option<usize> {
+    args.get("decimals")
+        .and_then(|v| v.as_u64())
+        .map(|d| d.min(12) as usize)
 }
 
 pub fn safe_url(args: &HashMap<String, Value>) -> tera::Result<Value> {
@@ -89,15 +135,177 @@ pub fn active_link(args: &HashMap<String, Value>) -> tera::Result<Value> {
     Ok(to_value(class).unwrap())
 }
 
+/// Page numbers of a listing of `total` items, `per` by page; `page` starts at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Pagination {
+    pub pages: usize,
+    pub page: usize,
+    pub per: usize,
+    pub prev: bool,
+    pub next: bool,
+}
+
+impl Pagination {
+    pub fn new(total: usize, page: usize, per: usize) -> Self {
+        let per = per.max(1);
+        let pages = total.div_ceil(per).max(1);
+        let page = page.clamp(1, pages);
+        Self {
+            pages,
+            page,
+            per,
+            prev: page > 1,
+            next: page < pages,
+        }
+    }
+
+    /// Indices of the items of the current page.
+    pub fn range(&self, total: usize) -> std::ops::Range<usize> {
+        let start = ((self.page - 1) * self.per).min(total);
+        start..(start + self.per).min(total)
+    }
+
+    /// First and last page, the pages within `size` of the current one, and `None`
+    /// where pages are left out: `1 … 4 5 6 … 10`. A single left-out page is shown
+    /// instead of an ellipsis.
+    pub fn window(&self, size: usize) -> Vec<Option<usize>> {
+        let (from, to) = (
+            self.page.saturating_sub(size).max(1),
+            (self.page + size).min(self.pages),
+        );
+        let mut numbers = Vec::new();
+        let push_gap = |numbers: &mut Vec<Option<usize>>, a: usize, b: usize| match b - a {
+            0 | 1 => {}
+            2 => numbers.push(Some(a + 1)),
+            _ => numbers.push(None),
+        };
+        numbers.push(Some(1));
+        if from > 1 {
+            push_gap(&mut numbers, 1, from);
+        }
+        numbers.extend((from.max(2)..=to).map(Some));
+        if to < self.pages {
+            push_gap(&mut numbers, to, self.pages);
+            numbers.push(Some(self.pages));
+        }
+        numbers
+    }
+}
+
+/// `{{ paginate(total=…, page=…, per=…) }}`: unlike [`Pagination::new`], `pages` is
+/// 0 for an empty listing and `page` is kept as given, out of range or not.
 pub fn paginate(args: &HashMap<String, Value>) -> tera::Result<Value> {
     let total = args.get("total").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
     let page = args.get("page").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
     let per = args.get("per").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
-    let pages = total.div_ceil(per);
-    let has_prev = page > 1;
-    let has_next = page < pages.max(1);
-    Ok(to_value(
-        serde_json::json!({"pages":pages,"page":page,"per":per,"prev":has_prev,"next":has_next}),
-    )
+    let pages = total.div_ceil(per.max(1));
+    Ok(to_value(Pagination {
+        pages,
+        page,
+        per,
+        prev: page > 1,
+        next: page < pages.max(1),
+    })
     .unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paginate_with(total: u64, page: u64, per: u64) -> Value {
+        let args = HashMap::from([
+            ("total".to_string(), Value::from(total)),
+            ("page".to_string(), Value::from(page)),
+            ("per".to_string(), Value::from(per)),
+        ]);
+        paginate(&args).unwrap()
+    }
+
+    #[test]
+    fn paginate_keeps_its_arguments() {
+        assert_eq!(
+            paginate_with(0, 1, 20),
+            serde_json::json!({"pages": 0, "page": 1, "per": 20, "prev": false, "next": false})
+        );
+        assert_eq!(
+            paginate_with(45, 2, 20),
+            serde_json::json!({"pages": 3, "page": 2, "per": 20, "prev": true, "next": true})
+        );
+        assert_eq!(
+            paginate_with(45, 7, 20),
+            serde_json::json!({"pages": 3, "page": 7, "per": 20, "prev": true, "next": false})
+        );
+    }
+
+    #[test]
+    fn pagination_is_clamped() {
+        let empty = Pagination::new(0, 3, 10);
+        assert_eq!((empty.pages, empty.page), (1, 1));
+        assert_eq!(empty.range(0), 0..0);
+        let last = Pagination::new(45, 9, 20);
+        assert_eq!(
+            (last.pages, last.page, last.prev, last.next),
+            (3, 3, true, false)
+        );
+        assert_eq!(last.range(45), 40..45);
+        assert_eq!(Pagination::new(5, 1, 0).per, 1);
+    }
+
+    #[test]
+    fn pagination_window() {
+        let window = |page, pages: usize| Pagination::new(pages * 10, page, 10).window(1);
+        assert_eq!(window(1, 1), [Some(1)]);
+        assert_eq!(window(1, 3), [Some(1), Some(2), Some(3)]);
+        assert_eq!(
+            window(5, 10),
+            [Some(1), None, Some(4), Some(5), Some(6), None, Some(10)]
+        );
+        // Une seule page omise s'affiche au lieu d'une ellipse
+        assert_eq!(
+            window(4, 6),
+            [Some(1), Some(2), Some(3), Some(4), Some(5), Some(6)]
+        );
+        assert_eq!(window(10, 10), [Some(1), None, Some(9), Some(10)]);
+    }
+
+    #[test]
+    fn former_number_helpers_keep_their_defaults() {
+        let call = |f: fn(&HashMap<String, Value>) -> tera::Result<Value>,
+                    args: &[(&str, Value)]| {
+            let args = args
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect();
+            f(&args).unwrap()
+        };
+        let n = |n: f64| ("n", to_value(n).unwrap());
+        assert_eq!(call(num_format, &[]), "0");
+        assert_eq!(call(num_format, &[n(1234567.4)]), "1 234 567");
+        assert_eq!(call(num_format, &[n(-1234.0)]), "-1 234");
+        assert_eq!(call(money_eur, &[]), "0,00 €");
+        assert_eq!(call(money_eur, &[n(1234.5)]), "1 234,50 €");
+        assert_eq!(call(money_eur, &[("n", to_value("x").unwrap())]), "0,00 €");
+    }
+
+    #[test]
+    fn percent_follows_the_site_language() {
+        let call = |lang: Option<&str>, args: &[(&str, Value)]| {
+            let mut config = Config::default();
+            config.seo.lang = lang.map(str::to_string);
+            let args = args
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect();
+            percent(&config, &args).unwrap()
+        };
+        let n = || ("n", to_value(0.126).unwrap());
+        assert_eq!(call(None, &[]), "0%");
+        assert_eq!(call(None, &[n()]), "13%");
+        assert_eq!(call(Some("fr"), &[n()]), "13\u{202f}%");
+        assert_eq!(
+            call(Some("fr"), &[n(), ("locale", to_value("en-US").unwrap())]),
+            "13%"
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Server-side highlighting of fenced code blocks, with syntect.
+//!
+//! Tokens get `hl-` prefixed classes rather than inline colors, so that the
+//! stylesheet exported by `rey highlight` can switch themes for dark mode. Every
+//! line is wrapped in `<span class="line">`; the info string may ask for line
+//! numbers and highlighted lines:
+//!
+//! ````markdown
+//! ```rust linenos {3,5-7}
+//! ````
+
+use crate::{config::HighlightConfig, render::helpers::escape};
+use comrak::adapters::SyntaxHighlighterAdapter;
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    sync::Mutex,
+};
+use syntect::{
+    highlighting::ThemeSet,
+    html::{ClassStyle, ClassedHTMLGenerator, css_for_theme_with_class_style},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+pub const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+static SYNTAXES: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEMES: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+/// Names of the bundled themes.
+pub fn themes() -> Vec<String> {
+    THEMES.themes.keys().cloned().collect()
+}
+
+/// Code block adapter for comrak. The info string after the language reaches
+/// [`SyntaxHighlighterAdapter::write_code_tag`] as `data-meta` (comrak's
+/// `full_info_string`), and is kept for the block being written.
+pub struct Highlighter {
+    line_numbers: bool,
+    meta: Mutex<Option<String>>,
+}
+
+impl Highlighter {
+    pub fn new(config: &HighlightConfig) -> Self {
+        Self {
+            line_numbers: config.line_numbers,
+            meta: Mutex::new(None),
+        }
+    }
+}
+
+impl SyntaxHighlighterAdapter for Highlighter {
+    fn write_highlighted(
+        &self,
+        output: &mut dyn Write,
+        lang: Option<&str>,
+        code: &str,
+    ) -> io::Result<()> {
+        let meta = self
+            .meta
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .unwrap_or_default();
+        let line_numbers = match () {
+            _ if meta.split_whitespace().any(|w| w == "nolinenos") => false,
+            _ if meta.split_whitespace().any(|w| w == "linenos") => true,
+            _ => self.line_numbers,
+        };
+        let marked = marked_lines(&meta);
+        let html = highlight(lang.unwrap_or_default(), code);
+        for (i, line) in split_lines(&html).iter().enumerate() {
+            let n = i + 1;
+            let class = if marked.iter().any(|(a, b)| (*a..=*b).contains(&n)) {
+                "line hl"
+            } else {
+                "line"
+            };
+            write!(output, "<span class=\"{class}\">")?;
+            if line_numbers {
+                write!(output, "<span class=\"ln\" aria-hidden=\"true\">{n}</span>")?;
+            }
+            // Le saut de ligne reste dans la ligne, pour le surlignage comme pour la copie
+            write!(output, "{line}\n</span>")?;
+        }
+        Ok(())
+    }
+
+    fn write_pre_tag(
+        &self,
+        output: &mut dyn Write,
+        mut attributes: HashMap<String, String>,
+    ) -> io::Result<()> {
+        let class = attributes.entry("class".to_string()).or_default();
+        class.insert_str(0, "highlight hl-code ");
+        *class = class.trim().to_string();
+        write_tag(output, "pre", &attributes)
+    }
+
+    fn write_code_tag(
+        &self,
+        output: &mut dyn Write,
+        mut attributes: HashMap<String, String>,
+    ) -> io::Result<()> {
+        *self.meta.lock().unwrap_or_else(|e| e.into_inner()) = attributes.remove("data-meta");
+        write_tag(output, "code", &attributes)
+    }
+}
+
+fn write_tag(
+    output: &mut dyn Write,
+    tag: &str,
+    attributes: &HashMap<String, String>,
+) -> io::Result<()> {
+    let mut names: Vec<&String> = attributes.keys().collect();
+    names.sort();
+    write!(output, "<{tag}")?;
+    for name in names {
+        write!(output, " {name}=\"{}\"", escape(&attributes[name]))?;
+    }
+    write!(output, ">")
+}
+
+/// Classed HTML of `code`, as plain text when `lang` is unknown.
+pub fn highlight(lang: &str, code: &str) -> String {
+    let syntax = SYNTAXES
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+    let mut html = ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAXES, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        if html
+            .parse_html_for_line_which_includes_newline(line)
+            .is_err()
+        {
+            return escape(code);
+        }
+    }
+    html.finalize()
+}
+
+/// Splits highlighted HTML into lines that each close the spans they open and
+/// reopen the ones left open by the previous line.
+fn split_lines(html: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut line = String::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        if rest.starts_with("</span>") {
+            open.pop();
+            line.push_str("</span>");
+            rest = &rest["</span>".len()..];
+        } else if rest.starts_with("<span")
+            && let Some(end) = rest.find('>')
+        {
+            open.push(&rest[..=end]);
+            line.push_str(&rest[..=end]);
+            rest = &rest[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('\n') {
+            line.push_str(&"</span>".repeat(open.len()));
+            lines.push(std::mem::take(&mut line));
+            line.extend(open.iter().copied());
+            rest = after;
+        } else {
+            let next = rest.find(['<', '\n']).unwrap_or(rest.len()).max(1);
+            line.push_str(&rest[..next]);
+            rest = &rest[next..];
+        }
+    }
+    // Une dernière ligne qui ne contient que des balises réouvertes est vide
+    if !is_only_tags(&line) {
+        line.push_str(&"</span>".repeat(open.len()));
+        lines.push(line);
+    }
+    lines
+}
+
+fn is_only_tags(s: &str) -> bool {
+    let mut inside = false;
+    for c in s.chars() {
+        match c {
+            '<' => inside = true,
+            '>' => inside = false,
+            _ if !inside => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Line ranges of `{3,5-7}` in an info string.
+fn marked_lines(meta: &str) -> Vec<(usize, usize)> {
+    let Some(start) = meta.find('{') else {
+        return Vec::new();
+    };
+    let Some(end) = meta[start..].find('}') else {
+        return Vec::new();
+    };
+    meta[start + 1..start + end]
+        .split(',')
+        .filter_map(|part| {
+            let part = part.trim();
+            match part.split_once('-') {
+                Some((a, b)) => Some((a.trim().parse().ok()?, b.trim().parse().ok()?)),
+                None => part.parse().ok().map(|n| (n, n)),
+            }
+        })
+        .collect()
+}
+
+/// SCSS of the `[highlight]` themes: the light one, then the dark one under
+/// `prefers-color-scheme: dark`, plus the rules of line numbers and marked lines.
+pub fn scss(config: &HighlightConfig) -> anyhow::Result<String> {
+    let css = |name: &str| -> anyhow::Result<String> {
+        let theme = THEMES.themes.get(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "unknown highlight theme `{name}` (available: {})",
+                themes().join(", ")
+            )
+        })?;
+        Ok(css_for_theme_with_class_style(theme, CLASS_STYLE)?)
+    };
+    let mut scss =
+        String::from("// Generated by `rey highlight`, from the [highlight] themes.\n\n");
+    scss.push_str(&css(&config.theme)?);
+    if let Some(dark) = &config.dark_theme {
+        scss.push_str("\n@media (prefers-color-scheme: dark) {\n");
+        for line in css(dark)?.lines() {
+            scss.push_str("  ");
+            scss.push_str(line);
+            scss.push('\n');
+        }
+        scss.push_str("}\n");
+    }
+    scss.push_str(
+        r#"
+pre.highlight {
+  overflow-x: auto;
+
+  .line {
+    display: inline-block;
+    width: 100%;
+  }
+
+  .line.hl {
+    background-color: rgba(255, 213, 0, 0.18);
+  }
+
+  .ln {
+    display: inline-block;
+    width: 3ch;
+    margin-right: 1ch;
+    text-align: right;
+    opacity: 0.5;
+    user-select: none;
+  }
+}
+"#,
+    );
+    Ok(scss)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, render::filters};
+
+    #[test]
+    fn lines_close_and_reopen_their_spans() {
+        let lines = split_lines("<span class=\"a\">one\ntwo</span>\n<span class=\"b\">x</span>\n");
+        assert_eq!(
+            lines,
+            [
+                "<span class=\"a\">one</span>",
+                "<span class=\"a\">two</span>",
+                "<span class=\"b\">x</span>",
+            ]
+        );
+        assert_eq!(split_lines("a\n\nb"), ["a", "", "b"]);
+        assert!(split_lines("").is_empty());
+    }
+
+    #[test]
+    fn marked_line_ranges() {
+        assert_eq!(marked_lines("{1,3-5}"), [(1, 1), (3, 5)]);
+        assert_eq!(marked_lines("linenos { 2 , x, 4-}"), [(2, 2)]);
+        assert!(marked_lines("linenos").is_empty());
+        assert!(marked_lines("{1").is_empty());
+    }
+
+    #[test]
+    fn unknown_languages_are_escaped_plain_text() {
+        let html = highlight("no-such-lang", "a < b && c\n");
+        assert!(html.contains("a &lt; b &amp;&amp; c"), "{html}");
+        assert!(!html.contains("a < b"), "{html}");
+    }
+
+    #[test]
+    fn fences_get_line_numbers_and_marks() {
+        let mut config = Config::default();
+        config.highlight.enabled = true;
+        config.highlight.line_numbers = false;
+        let html = filters::render_markdown(
+            &config,
+            "```rust linenos {2}\nlet a = 1;\nlet b = 2;\n```\n",
+        );
+        assert!(html.contains("<pre class=\"highlight hl-code\""), "{html}");
+        assert_eq!(html.matches("<span class=\"line\">").count(), 1, "{html}");
+        assert_eq!(
+            html.matches("<span class=\"line hl\">").count(),
+            1,
+            "{html}"
+        );
+        assert!(
+            html.contains("<span class=\"ln\" aria-hidden=\"true\">2</span>"),
+            "{html}"
+        );
+        assert!(!html.contains("data-meta"), "{html}");
+    }
+}
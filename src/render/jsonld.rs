@@ -1 +1,540 @@
+//! Schema.org JSON-LD built from a [`Seo`].
+//!
+//! The graph always holds the `WebSite`, then the main entity picked from
+//! `Seo::content_type` ("work" → `Book`, "author" → `Person`, "season" →
+//! `CreativeWorkSeason`, "event" → `Event`, otherwise `BlogPosting` for articles)
+//! and a `BreadcrumbList` derived from the canonical URL. Parts of a series, and
+//! its landing page, also get the `CreativeWorkSeries` their `isPartOf` points to.
 
+use crate::{Seo, config::SiteConfig};
+use rocket_dyn_templates::tera;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const SCHEMA_CONTEXT: &str = "https://schema.org";
+
+#[derive(Debug, Serialize)]
+pub struct Graph {
+    #[serde(rename = "@context")]
+    pub context: &'static str,
+    #[serde(rename = "@graph")]
+    pub graph: Vec<Node>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Node {
+    WebSite(WebSite),
+    CreativeWork(CreativeWork),
+    Person(Person),
+    Event(Event),
+    BreadcrumbList(BreadcrumbList),
+    Series(CreativeWorkSeries),
+    /// Hand-written `Seo::json_ld`, kept as is.
+    Raw(Value),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSite {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    #[serde(rename = "@id")]
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_language: Option<String>,
+}
+
+/// `BlogPosting`, `Article`, `Book` or `CreativeWorkSeason`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreativeWork {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    #[serde(rename = "@id")]
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headline: Option<String>,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<Person>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_modified: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_part_of: Option<Reference>,
+    /// Part number within the series of `is_part_of`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub main_entity_of_page: Option<String>,
+}
+
+/// The series of `Extra::series`, target of the `isPartOf` of its parts.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreativeWorkSeries {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    #[serde(rename = "@id")]
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub is_part_of: Reference,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    #[serde(rename = "@id")]
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Place>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organizer: Option<Person>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Place {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreadcrumbList {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub item_list_element: Vec<ListItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListItem {
+    #[serde(rename = "@type")]
+    pub kind: &'static str,
+    pub position: usize,
+    pub name: String,
+    pub item: String,
+}
+
+/// Link to another node of the graph (or of another page).
+#[derive(Debug, Serialize)]
+pub struct Reference {
+    #[serde(rename = "@type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<&'static str>,
+    #[serde(rename = "@id")]
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Values a `Seo` does not carry but some types need.
+#[derive(Debug, Default)]
+pub struct Extra {
+    /// `datePublished` (the page date), or `startDate` for an event.
+    pub published: Option<String>,
+    /// `endDate` of an event.
+    pub end: Option<String>,
+    /// Place name of an event.
+    pub location: Option<String>,
+    /// Series the page is a part of: title and absolute URL of its landing page.
+    pub series: Option<(String, String)>,
+    /// Position of the page in `series`.
+    pub position: Option<usize>,
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.trim().is_empty()).then(|| s.to_string())
+}
+
+/// Resolves a site-relative URL (`/public/img/cover.jpg`) against the site root.
+pub(crate) fn absolute(root: &str, url: &str) -> String {
+    match url.strip_prefix('/') {
+        Some(path) => format!("{root}{path}"),
+        None => url.to_string(),
+    }
+}
+
+pub(crate) fn site_url(seo: &Seo, site: Option<&SiteConfig>) -> String {
+    if let Some(site) = site {
+        return format!("{}/", site.base_url.trim_end_matches('/'));
+    }
+    // Sans configuration, on retombe sur l'origine de l'URL canonique
+    let canonical = seo.canonical_url.as_deref().unwrap_or("/");
+    match canonical.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split('/').next().unwrap_or(rest);
+            format!("{scheme}://{host}/")
+        }
+        None => "/".to_string(),
+    }
+}
+
+/// Builds the Schema.org graph of a page; a `Seo::json_ld` that is not JSON is an
+/// error.
+pub fn graph(seo: &Seo, site: Option<&SiteConfig>, extra: &Extra) -> serde_json::Result<Graph> {
+    let root = site_url(seo, site);
+    let url = seo.canonical_url.clone().unwrap_or_else(|| root.clone());
+    let website_id = format!("{root}#website");
+    let image = seo.og_image.as_deref().map(|i| absolute(&root, i));
+    let mut graph = vec![Node::WebSite(WebSite {
+        kind: "WebSite",
+        id: website_id.clone(),
+        name: site
+            .and_then(|s| non_empty(&s.title))
+            .unwrap_or_else(|| seo.title.clone()),
+        url: root.clone(),
+        description: site.and_then(|s| non_empty(&s.description)),
+        in_language: seo.lang.clone(),
+    })];
+
+    let author = || {
+        seo.author
+            .as_deref()
+            .and_then(non_empty)
+            .map(|name| Person {
+                kind: "Person",
+                name,
+                url: None,
+                description: None,
+                image: None,
+            })
+    };
+    let work = |kind| CreativeWork {
+        kind,
+        id: format!("{url}#main"),
+        headline: (kind == "BlogPosting" || kind == "Article").then(|| seo.title.clone()),
+        name: seo.title.clone(),
+        description: non_empty(&seo.description),
+        url: url.clone(),
+        image: image.clone(),
+        author: author(),
+        date_published: extra.published.clone(),
+        date_modified: seo.updated.clone(),
+        in_language: seo.lang.clone(),
+        keywords: (!seo.keywords.is_empty()).then(|| seo.keywords.join(", ")),
+        is_part_of: Some(match &extra.series {
+            Some((name, series_url)) => Reference {
+                kind: Some("CreativeWorkSeries"),
+                id: format!("{series_url}#series"),
+                name: Some(name.clone()),
+            },
+            None => Reference {
+                kind: None,
+                id: website_id.clone(),
+                name: None,
+            },
+        }),
+        position: extra.series.as_ref().and(extra.position),
+        main_entity_of_page: Some(url.clone()),
+    };
+
+    match seo.content_type.as_deref() {
+        Some("work") => graph.push(Node::CreativeWork(work("Book"))),
+        Some("season") => graph.push(Node::CreativeWork(work("CreativeWorkSeason"))),
+        Some("author") => graph.push(Node::Person(Person {
+            kind: "Person",
+            name: seo.title.clone(),
+            url: Some(url.clone()),
+            description: non_empty(&seo.description),
+            image: image.clone(),
+        })),
+        Some("event") => graph.push(Node::Event(Event {
+            kind: "Event",
+            id: format!("{url}#main"),
+            name: seo.title.clone(),
+            description: non_empty(&seo.description),
+            url: url.clone(),
+            image: image.clone(),
+            start_date: extra.published.clone(),
+            end_date: extra.end.clone(),
+            location: extra
+                .location
+                .as_deref()
+                .and_then(non_empty)
+                .map(|name| Place {
+                    kind: "Place",
+                    name,
+                }),
+            organizer: author(),
+        })),
+        _ => match seo.og_type.as_deref() {
+            Some("article") => graph.push(Node::CreativeWork(work("BlogPosting"))),
+            Some("book") => graph.push(Node::CreativeWork(work("Book"))),
+            _ => {}
+        },
+    }
+
+    if let Some((name, series_url)) = &extra.series {
+        graph.push(Node::Series(CreativeWorkSeries {
+            kind: "CreativeWorkSeries",
+            id: format!("{series_url}#series"),
+            name: name.clone(),
+            url: series_url.clone(),
+            is_part_of: Reference {
+                kind: None,
+                id: website_id.clone(),
+                name: None,
+            },
+        }));
+    }
+
+    let crumbs = breadcrumbs(seo, &root, &url);
+    if crumbs.item_list_element.len() > 1 {
+        graph.push(Node::BreadcrumbList(crumbs));
+    }
+
+    if let Some(raw) = seo.json_ld.as_deref() {
+        match serde_json::from_str::<Value>(raw)? {
+            Value::Array(nodes) => graph.extend(nodes.into_iter().map(Node::Raw)),
+            value => graph.push(Node::Raw(value)),
+        }
+    }
+
+    Ok(Graph {
+        context: SCHEMA_CONTEXT,
+        graph,
+    })
+}
+
+/// Home, then one item per path segment of `url`; the last one is named after the page.
+pub fn breadcrumbs(seo: &Seo, root: &str, url: &str) -> BreadcrumbList {
+    let mut items = vec![ListItem {
+        kind: "ListItem",
+        position: 1,
+        name: "Home".to_string(),
+        item: root.to_string(),
+    }];
+    let path = url.strip_prefix(root).unwrap_or("");
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|s| !s.is_empty() && !s.ends_with(".html"))
+        .collect();
+    let mut current = root.to_string();
+    for (i, segment) in segments.iter().enumerate() {
+        current.push_str(segment);
+        current.push('/');
+        let name = if i + 1 == segments.len() && !seo.title.is_empty() {
+            seo.title.clone()
+        } else {
+            segment.replace('-', " ")
+        };
+        items.push(ListItem {
+            kind: "ListItem",
+            position: i + 2,
+            name,
+            item: current.clone(),
+        });
+    }
+    BreadcrumbList {
+        kind: "BreadcrumbList",
+        item_list_element: items,
+    }
+}
+
+/// Serializes the graph inside a `<script type="application/ld+json">` block.
+/// `<` is escaped so that the content can never close the script element.
+pub fn to_script(graph: &Graph) -> String {
+    let json = serde_json::to_string(graph)
+        .unwrap_or_default()
+        .replace('<', "\\u003c");
+    format!(r#"<script type="application/ld+json">{json}</script>"#)
+}
+
+/// `{{ jsonld(seo=seo, site=site, published=page.date) | safe }}`
+///
+/// Optional arguments: `published`, `end` and `location` (events), and `series`
+/// (the `series` of a part or of its landing page, adding the series node the
+/// page is `isPartOf`).
+pub fn jsonld(args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+    let seo: Seo = match args.get("seo") {
+        Some(v) => serde_json::from_value(v.clone())
+            .map_err(|e| tera::Error::msg(format!("jsonld: invalid `seo` argument: {e}")))?,
+        None => return Err(tera::Error::msg("jsonld: missing `seo` argument")),
+    };
+    let site: Option<SiteConfig> = args
+        .get("site")
+        .and_then(|v| serde_json::from_value(v.clone()).ok());
+    let arg = |k: &str| args.get(k).and_then(|v| v.as_str()).map(str::to_string);
+    let extra = Extra {
+        published: arg("published"),
+        end: arg("end"),
+        location: arg("location"),
+        series: args.get("series").and_then(|s| {
+            let title = s.get("title")?.as_str()?;
+            let url = s.get("url")?.as_str()?;
+            Some((title.to_string(), url.to_string()))
+        }),
+        position: args
+            .get("series")
+            .and_then(|s| s.get("position"))
+            .and_then(|p| p.as_u64())
+            .map(|p| p as usize),
+    };
+    let graph = graph(&seo, site.as_ref(), &extra).map_err(|e| {
+        tera::Error::msg(format!(
+            "jsonld: invalid `json_ld` of {}: {e}",
+            seo.canonical_url.as_deref().unwrap_or(&seo.title)
+        ))
+    })?;
+    Ok(tera::to_value(to_script(&graph)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn article() -> Seo {
+        let mut seo = Seo::new();
+        seo.with_title("Hello")
+            .with_canonical("https://ex.com/blog/hello/")
+            .with_og_type("article")
+            .with_updated("2025-02-01");
+        seo
+    }
+
+    fn graph_of(seo: &Seo, extra: &Extra) -> Graph {
+        graph(seo, None, extra).unwrap()
+    }
+
+    fn main_entity(graph: &Graph) -> Value {
+        serde_json::to_value(&graph.graph[1]).unwrap()
+    }
+
+    #[test]
+    fn published_date_is_the_page_date_only() {
+        let post = main_entity(&graph_of(&article(), &Extra::default()));
+        assert_eq!(post["@type"], "BlogPosting");
+        assert_eq!(post["dateModified"], "2025-02-01");
+        assert!(post.get("datePublished").is_none());
+
+        let extra = Extra {
+            published: Some("2025-01-01".to_string()),
+            ..Extra::default()
+        };
+        let post = main_entity(&graph_of(&article(), &extra));
+        assert_eq!(post["datePublished"], "2025-01-01");
+    }
+
+    #[test]
+    fn json_ld_overrides_are_appended_or_rejected() {
+        let mut seo = article();
+        seo.with_json_ld(r#"[{"@type": "FAQPage"}, {"@type": "HowTo"}]"#);
+        let types: Vec<Value> = graph_of(&seo, &Extra::default())
+            .graph
+            .iter()
+            .map(|n| serde_json::to_value(n).unwrap()["@type"].clone())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "WebSite",
+                "BlogPosting",
+                "BreadcrumbList",
+                "FAQPage",
+                "HowTo"
+            ]
+        );
+
+        seo.with_json_ld("{ not json");
+        assert!(graph(&seo, None, &Extra::default()).is_err());
+        let mut args = HashMap::new();
+        args.insert("seo".to_string(), serde_json::to_value(&seo).unwrap());
+        let err = jsonld(&args).unwrap_err().to_string();
+        assert!(err.contains("https://ex.com/blog/hello/"), "{err}");
+    }
+
+    #[test]
+    fn parts_point_to_a_series_node_of_the_graph() {
+        let extra = Extra {
+            series: Some((
+                "Rocket".to_string(),
+                "https://ex.com/series/rocket/".to_string(),
+            )),
+            position: Some(2),
+            ..Extra::default()
+        };
+        let graph = serde_json::to_value(graph_of(&article(), &extra)).unwrap();
+        let nodes = graph["@graph"].as_array().unwrap();
+        let post = &nodes[1];
+        assert_eq!(
+            post["isPartOf"]["@id"],
+            "https://ex.com/series/rocket/#series"
+        );
+        assert_eq!(post["position"], 2);
+        let series: Vec<&Value> = nodes
+            .iter()
+            .filter(|n| n["@id"] == post["isPartOf"]["@id"])
+            .collect();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0]["@type"], "CreativeWorkSeries");
+        assert_eq!(series[0]["name"], "Rocket");
+        assert_eq!(series[0]["isPartOf"]["@id"], "https://ex.com/#website");
+
+        // Sans série, aucun nœud de série
+        let graph = graph_of(&article(), &Extra::default());
+        assert!(!graph.graph.iter().any(|n| matches!(n, Node::Series(_))));
+    }
+
+    #[test]
+    fn breadcrumbs_follow_the_path() {
+        let list = breadcrumbs(&article(), "https://ex.com/", "https://ex.com/blog/hello/");
+        let names: Vec<&str> = list
+            .item_list_element
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect();
+        assert_eq!(names, ["Home", "blog", "Hello"]);
+        assert_eq!(list.item_list_element[2].item, "https://ex.com/blog/hello/");
+    }
+
+    #[test]
+    fn scripts_cannot_be_closed_early() {
+        let mut seo = article();
+        seo.with_title("</script><script>alert(1)");
+        let script = to_script(&graph_of(&seo, &Extra::default()));
+        assert_eq!(script.matches("</script>").count(), 1);
+    }
+}
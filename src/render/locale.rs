@@ -0,0 +1,246 @@
+//! Languages of the `date` and `ago` filters: month and day names and relative
+//! times ("3 days ago", "dans 3 jours"), with their plural rules.
+
+use chrono::{Datelike, TimeDelta};
+
+/// A language of the filters, read from a BCP 47 tag (`fr`, `fr-CA`, `en_GB`…).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Fr,
+}
+
+impl Lang {
+    /// Language of `tag`, English for the languages without a table.
+    pub fn from_tag(tag: &str) -> Self {
+        let language = tag.trim().split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "fr" => Self::Fr,
+            _ => Self::En,
+        }
+    }
+
+    fn months(self) -> [&'static str; 12] {
+        match self {
+            Self::En => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            Self::Fr => [
+                "janvier",
+                "février",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "août",
+                "septembre",
+                "octobre",
+                "novembre",
+                "décembre",
+            ],
+        }
+    }
+
+    fn months_abbr(self) -> [&'static str; 12] {
+        match self {
+            Self::En => [
+                "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+            ],
+            Self::Fr => [
+                "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+                "nov.", "déc.",
+            ],
+        }
+    }
+
+    /// Days of the week, from Monday.
+    fn weekdays(self) -> [&'static str; 7] {
+        match self {
+            Self::En => [
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+                "Sunday",
+            ],
+            Self::Fr => [
+                "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+            ],
+        }
+    }
+
+    fn weekdays_abbr(self) -> [&'static str; 7] {
+        match self {
+            Self::En => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+            Self::Fr => ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+        }
+    }
+
+    /// `fmt` with its `%B`, `%b` (`%h`), `%A` and `%a` replaced by the names of
+    /// `date` in this language, the rest being left to chrono.
+    pub fn localize_format(self, fmt: &str, date: &impl Datelike) -> String {
+        let month = date.month0() as usize;
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        let mut out = String::with_capacity(fmt.len());
+        let mut chars = fmt.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('B') => out.push_str(self.months()[month]),
+                Some('b' | 'h') => out.push_str(self.months_abbr()[month]),
+                Some('A') => out.push_str(self.weekdays()[weekday]),
+                Some('a') => out.push_str(self.weekdays_abbr()[weekday]),
+                Some(next) => {
+                    out.push('%');
+                    out.push(next);
+                }
+                None => out.push('%'),
+            }
+        }
+        out
+    }
+
+    /// Relative time of a date `delta` before now, or after it when negative:
+    /// "5 minutes ago", "in 3 days", "il y a 1 heure", "dans 2 ans".
+    pub fn relative(self, delta: TimeDelta) -> String {
+        let future = delta < TimeDelta::zero();
+        let seconds = delta.num_seconds().unsigned_abs();
+        let (n, unit) = match seconds {
+            0..45 => return self.now().to_string(),
+            45..3_600 => ((seconds / 60).max(1), Unit::Minute),
+            3_600..86_400 => (seconds / 3_600, Unit::Hour),
+            86_400..604_800 => (seconds / 86_400, Unit::Day),
+            604_800..2_592_000 => (seconds / 604_800, Unit::Week),
+            2_592_000..31_536_000 => (seconds / 2_592_000, Unit::Month),
+            _ => (seconds / 31_536_000, Unit::Year),
+        };
+        let amount = format!("{n} {}", self.unit(unit, n));
+        match (self, future) {
+            (Self::En, false) => format!("{amount} ago"),
+            (Self::En, true) => format!("in {amount}"),
+            (Self::Fr, false) => format!("il y a {amount}"),
+            (Self::Fr, true) => format!("dans {amount}"),
+        }
+    }
+
+    fn now(self) -> &'static str {
+        match self {
+            Self::En => "just now",
+            Self::Fr => "à l’instant",
+        }
+    }
+
+    /// Name of `unit` for `n` of them: French uses the singular for 0 and 1,
+    /// English for 1 only.
+    fn unit(self, unit: Unit, n: u64) -> &'static str {
+        let plural = match self {
+            Self::En => n != 1,
+            Self::Fr => n > 1,
+        };
+        let (one, many) = match (self, unit) {
+            (Self::En, Unit::Minute) => ("minute", "minutes"),
+            (Self::En, Unit::Hour) => ("hour", "hours"),
+            (Self::En, Unit::Day) => ("day", "days"),
+            (Self::En, Unit::Week) => ("week", "weeks"),
+            (Self::En, Unit::Month) => ("month", "months"),
+            (Self::En, Unit::Year) => ("year", "years"),
+            (Self::Fr, Unit::Minute) => ("minute", "minutes"),
+            (Self::Fr, Unit::Hour) => ("heure", "heures"),
+            (Self::Fr, Unit::Day) => ("jour", "jours"),
+            (Self::Fr, Unit::Week) => ("semaine", "semaines"),
+            (Self::Fr, Unit::Month) => ("mois", "mois"),
+            (Self::Fr, Unit::Year) => ("an", "ans"),
+        };
+        if plural { many } else { one }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Unit {
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn languages_of_tags() {
+        assert_eq!(Lang::from_tag("fr"), Lang::Fr);
+        assert_eq!(Lang::from_tag(" FR-ca "), Lang::Fr);
+        assert_eq!(Lang::from_tag("fr_BE"), Lang::Fr);
+        assert_eq!(Lang::from_tag("en-GB"), Lang::En);
+        assert_eq!(Lang::from_tag("de"), Lang::En);
+        assert_eq!(Lang::from_tag(""), Lang::En);
+    }
+
+    #[test]
+    fn french_uses_the_singular_for_zero_and_one() {
+        assert_eq!(Lang::Fr.unit(Unit::Day, 0), "jour");
+        assert_eq!(Lang::Fr.unit(Unit::Day, 1), "jour");
+        assert_eq!(Lang::Fr.unit(Unit::Day, 2), "jours");
+        assert_eq!(Lang::Fr.unit(Unit::Month, 1), "mois");
+        assert_eq!(Lang::En.unit(Unit::Day, 0), "days");
+        assert_eq!(Lang::En.unit(Unit::Day, 1), "day");
+    }
+
+    #[test]
+    fn relative_times() {
+        let s = TimeDelta::seconds;
+        assert_eq!(Lang::En.relative(s(0)), "just now");
+        assert_eq!(Lang::En.relative(s(44)), "just now");
+        assert_eq!(Lang::Fr.relative(s(-44)), "à l’instant");
+        assert_eq!(Lang::En.relative(s(45)), "1 minute ago");
+        assert_eq!(Lang::En.relative(s(3_599)), "59 minutes ago");
+        assert_eq!(Lang::En.relative(s(3_600)), "1 hour ago");
+        assert_eq!(Lang::Fr.relative(s(86_399)), "il y a 23 heures");
+        assert_eq!(Lang::Fr.relative(s(86_400)), "il y a 1 jour");
+        assert_eq!(Lang::En.relative(s(604_800)), "1 week ago");
+        assert_eq!(Lang::En.relative(s(2_592_000)), "1 month ago");
+        assert_eq!(Lang::Fr.relative(s(31_535_999)), "il y a 12 mois");
+        assert_eq!(Lang::Fr.relative(s(31_536_000)), "il y a 1 an");
+        // Les dates à venir ont un delta négatif
+        assert_eq!(Lang::En.relative(s(-7_200)), "in 2 hours");
+        assert_eq!(Lang::Fr.relative(s(-3 * 86_400)), "dans 3 jours");
+        assert_eq!(Lang::Fr.relative(s(-2 * 31_536_000)), "dans 2 ans");
+    }
+
+    #[test]
+    fn formats_get_the_names_of_the_language() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 5).unwrap();
+        let format = |lang: Lang, fmt: &str| {
+            let fmt = lang.localize_format(fmt, &date);
+            date.format(&fmt).to_string()
+        };
+        assert_eq!(format(Lang::Fr, "%A %e %B %Y"), "lundi  5 février 2024");
+        assert_eq!(format(Lang::Fr, "%a %d %b / %h"), "lun. 05 févr. / févr.");
+        assert_eq!(format(Lang::En, "%a, %B %d"), "Mon, February 05");
+        // `%%` reste un `%` littéral, même suivi d'une lettre de nom
+        assert_eq!(format(Lang::Fr, "100%% %B"), "100% février");
+        assert_eq!(format(Lang::Fr, "%%B"), "%B");
+        assert_eq!(Lang::Fr.localize_format("50 %", &date), "50 %");
+    }
+}
@@ -0,0 +1,225 @@
+//! Numbers, amounts of money and percentages written the way each locale does:
+//! `1,234.5` in the US, `1 234,5` in France (with a narrow no-break space), `1.234,5`
+//! in Germany, `-$12.00` and `-12,00 €`.
+
+/// No-break space, before the currency symbol in French and German.
+const NBSP: &str = "\u{a0}";
+/// Narrow no-break space, the French thousands separator and space before `%`.
+const NNBSP: &str = "\u{202f}";
+
+/// A locale of the number helpers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    EnUs,
+    EnGb,
+    FrFr,
+    DeDe,
+}
+
+impl Locale {
+    /// Locale of a BCP 47 tag (`fr`, `de-AT`, `en_GB`…): the language picks it, `en`
+    /// being `en-US` unless the region is `GB`; `en-US` for the other languages.
+    pub fn from_tag(tag: &str) -> Self {
+        let tag = tag.trim().replace('_', "-").to_ascii_lowercase();
+        let (language, region) = tag.split_once('-').unwrap_or((&tag, ""));
+        match (language, region) {
+            ("fr", _) => Self::FrFr,
+            ("de", _) => Self::DeDe,
+            ("en", "gb" | "uk") => Self::EnGb,
+            _ => Self::EnUs,
+        }
+    }
+
+    fn group(self) -> &'static str {
+        match self {
+            Self::EnUs | Self::EnGb => ",",
+            Self::FrFr => NNBSP,
+            Self::DeDe => ".",
+        }
+    }
+
+    fn decimal(self) -> &'static str {
+        match self {
+            Self::EnUs | Self::EnGb => ".",
+            Self::FrFr | Self::DeDe => ",",
+        }
+    }
+
+    /// `n` with `decimals` digits after the separator, or up to 3 without trailing
+    /// zeros when `None`.
+    pub fn number(self, n: f64, decimals: Option<usize>) -> String {
+        let (negative, digits) = match decimals {
+            Some(d) => fixed(n, d),
+            None => {
+                let (negative, digits) = fixed(n, 3);
+                let digits = digits.trim_end_matches('0').trim_end_matches('.');
+                (negative, digits.to_string())
+            }
+        };
+        let body = self.grouped(&digits);
+        if negative { format!("-{body}") } else { body }
+    }
+
+    /// `n` of `currency` (ISO 4217), with its usual number of decimals by default.
+    pub fn currency(self, n: f64, currency: &str, decimals: Option<usize>) -> String {
+        let currency = currency.trim().to_ascii_uppercase();
+        let decimals = decimals.unwrap_or_else(|| minor_units(&currency));
+        let (negative, digits) = fixed(n, decimals);
+        let body = self.grouped(&digits);
+        let symbol = self.symbol(&currency);
+        let sign = if negative { "-" } else { "" };
+        match self {
+            // Les codes ISO gardent une espace pour ne pas se coller au nombre
+            Self::EnUs | Self::EnGb if symbol.chars().all(|c| c.is_ascii_alphabetic()) => {
+                format!("{sign}{symbol}{NBSP}{body}")
+            }
+            Self::EnUs | Self::EnGb => format!("{sign}{symbol}{body}"),
+            Self::FrFr | Self::DeDe => format!("{sign}{body}{NBSP}{symbol}"),
+        }
+    }
+
+    /// `ratio` as a percentage: `0.125` → `12.5%`, `12,5 %`.
+    pub fn percent(self, ratio: f64, decimals: usize) -> String {
+        let number = self.number(ratio * 100.0, Some(decimals));
+        match self {
+            Self::EnUs | Self::EnGb => format!("{number}%"),
+            Self::FrFr => format!("{number}{NNBSP}%"),
+            Self::DeDe => format!("{number}{NBSP}%"),
+        }
+    }
+
+    /// Symbol of `currency` in this locale, the ISO code for the unknown ones.
+    fn symbol(self, currency: &str) -> String {
+        let symbol = match (currency, self) {
+            ("EUR", _) => "€",
+            ("USD", Self::EnUs | Self::DeDe) => "$",
+            ("USD", Self::EnGb) => "US$",
+            ("USD", Self::FrFr) => "$US",
+            ("GBP", Self::FrFr) => "£GB",
+            ("GBP", _) => "£",
+            ("JPY", Self::FrFr) => "JPY",
+            ("JPY", _) => "¥",
+            ("CAD", Self::EnUs | Self::EnGb) => "CA$",
+            ("CAD", Self::FrFr) => "$CA",
+            ("CAD", Self::DeDe) => "CA$",
+            (code, _) => code,
+        };
+        symbol.to_string()
+    }
+
+    /// Groups the integer part of the ASCII `digits` (`1234.50`) by thousands.
+    fn grouped(self, digits: &str) -> String {
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        let mut out = String::with_capacity(digits.len() + int.len() / 3 * 3);
+        for (i, c) in int.chars().enumerate() {
+            if i > 0 && (int.len() - i) % 3 == 0 {
+                out.push_str(self.group());
+            }
+            out.push(c);
+        }
+        if !frac.is_empty() {
+            out.push_str(self.decimal());
+            out.push_str(frac);
+        }
+        out
+    }
+}
+
+/// Sign and ASCII digits of `n` rounded to `decimals`; a value rounded to zero is
+/// not negative.
+fn fixed(n: f64, decimals: usize) -> (bool, String) {
+    let digits = format!("{:.*}", decimals, n.abs());
+    let zero = digits.chars().all(|c| c == '0' || c == '.');
+    (n.is_sign_negative() && !zero, digits)
+}
+
+/// Decimals of the amounts of `currency`.
+fn minor_units(currency: &str) -> usize {
+    match currency {
+        "JPY" | "KRW" | "CLP" | "ISK" | "VND" => 0,
+        "BHD" | "KWD" | "OMR" | "TND" | "JOD" => 3,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCALES: [Locale; 4] = [Locale::EnUs, Locale::EnGb, Locale::FrFr, Locale::DeDe];
+
+    fn each(f: impl Fn(Locale) -> String) -> [String; 4] {
+        LOCALES.map(f)
+    }
+
+    #[test]
+    fn locales_of_tags() {
+        assert_eq!(Locale::from_tag("fr-CA"), Locale::FrFr);
+        assert_eq!(Locale::from_tag("de_AT"), Locale::DeDe);
+        assert_eq!(Locale::from_tag("en_GB"), Locale::EnGb);
+        assert_eq!(Locale::from_tag("en-uk"), Locale::EnGb);
+        assert_eq!(Locale::from_tag("EN"), Locale::EnUs);
+        assert_eq!(Locale::from_tag("ja"), Locale::EnUs);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            each(|l| l.number(1_234_567.891, None)),
+            [
+                "1,234,567.891",
+                "1,234,567.891",
+                "1\u{202f}234\u{202f}567,891",
+                "1.234.567,891"
+            ]
+        );
+        assert_eq!(
+            each(|l| l.number(-1234.5, Some(2))),
+            ["-1,234.50", "-1,234.50", "-1\u{202f}234,50", "-1.234,50"]
+        );
+        assert_eq!(Locale::EnUs.number(999.0, None), "999");
+        assert_eq!(Locale::EnUs.number(1.5, Some(0)), "2");
+        assert_eq!(Locale::EnUs.number(2.0004, None), "2");
+        // Un négatif arrondi à zéro perd son signe
+        assert_eq!(Locale::FrFr.number(-0.0004, None), "0");
+        assert_eq!(Locale::DeDe.number(-0.001, Some(2)), "0,00");
+        assert_eq!(Locale::EnUs.number(-0.0, Some(1)), "0.0");
+    }
+
+    #[test]
+    fn currencies() {
+        assert_eq!(
+            each(|l| l.currency(1234.5, "eur", None)),
+            [
+                "€1,234.50",
+                "€1,234.50",
+                "1\u{202f}234,50\u{a0}€",
+                "1.234,50\u{a0}€"
+            ]
+        );
+        assert_eq!(
+            each(|l| l.currency(-12.0, "USD", None)),
+            ["-$12.00", "-US$12.00", "-12,00\u{a0}$US", "-12,00\u{a0}$"]
+        );
+        assert_eq!(Locale::EnUs.currency(-0.004, "USD", None), "$0.00");
+        // Le yen n'a pas de centimes, le dinar koweïtien a des millièmes
+        assert_eq!(Locale::EnGb.currency(1234.6, "JPY", None), "¥1,235");
+        assert_eq!(
+            Locale::FrFr.currency(1234.0, "JPY", None),
+            "1\u{202f}234\u{a0}JPY"
+        );
+        assert_eq!(Locale::EnUs.currency(1.5, "KWD", None), "KWD\u{a0}1.500");
+        assert_eq!(Locale::DeDe.currency(1.5, "KWD", Some(1)), "1,5\u{a0}KWD");
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(
+            each(|l| l.percent(0.125, 1)),
+            ["12.5%", "12.5%", "12,5\u{202f}%", "12,5\u{a0}%"]
+        );
+        assert_eq!(Locale::EnUs.percent(-0.25, 0), "-25%");
+        assert_eq!(Locale::FrFr.percent(-0.001, 0), "0\u{202f}%");
+        assert_eq!(Locale::EnUs.percent(12.346, 0), "1,235%");
+    }
+}
@@ -0,0 +1,469 @@
+//! Shortcodes of the markdown of `content/`, rendered by the templates of
+//! `templates/shortcodes/`:
+//!
+//! ```markdown
+//! {{ youtube(id="dQw4w9WgXcQ", start=30) }}
+//!
+//! {% callout(kind="warning") %}
+//! The **body** is passed to the template as `body`.
+//! {% end %}
+//! ```
+//!
+//! The arguments are the context of the template, along with `nth`, the number of
+//! the call of this shortcode in the file. An HTML template (`youtube.html.tera`)
+//! is inserted as is after the markdown rendering, a markdown one (`youtube.md.tera`)
+//! before it. Shortcodes are left alone in code blocks and code spans.
+
+use anyhow::{Context as _, anyhow, bail};
+use rocket_dyn_templates::tera::{Context, Tera};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const SHORTCODES_DIR: &str = "shortcodes";
+
+/// Markdown whose shortcodes were expanded, the HTML ones being replaced by
+/// placeholders until [`Expanded::restore`].
+pub struct Expanded {
+    pub markdown: String,
+    html: Vec<String>,
+}
+
+impl Expanded {
+    /// Puts the HTML of the shortcodes back into the rendered `html`.
+    pub fn restore(&self, html: &str) -> String {
+        let mut html = html.to_string();
+        for (i, output) in self.html.iter().enumerate() {
+            let token = placeholder(i);
+            // Seul sur sa ligne, le shortcode a été mis dans un paragraphe
+            html = html
+                .replace(&format!("<p>{token}</p>"), output)
+                .replace(&token, output);
+        }
+        html
+    }
+}
+
+fn placeholder(i: usize) -> String {
+    format!("REYSHORTCODE{i}X")
+}
+
+/// Expands the shortcodes of `markdown`, the body of `file` starting at line
+/// `first_line`, with the `shortcodes/*` templates of `tera`.
+pub fn expand(
+    tera: &Tera,
+    file: &str,
+    first_line: usize,
+    markdown: &str,
+) -> anyhow::Result<Expanded> {
+    let mut expander = Expander {
+        tera,
+        file,
+        first_line,
+        markdown,
+        nth: HashMap::new(),
+        html: Vec::new(),
+    };
+    let mut out = String::with_capacity(markdown.len());
+    let mut fence: Option<(char, usize)> = None;
+    let mut i = 0;
+    // Chaque tour part d'un début de ligne
+    while i < markdown.len() {
+        let rest = &markdown[i..];
+        let line = &rest[..rest.find('\n').map_or(rest.len(), |n| n + 1)];
+        match (fence, fence_of(line)) {
+            (Some((c, n)), Some((d, m)))
+                if c == d && m >= n && line.trim() == &line.trim()[..m] =>
+            {
+                fence = None
+            }
+            (Some(_), _) => {}
+            (None, Some(open)) => fence = Some(open),
+            (None, None) => {
+                i = expander.line(i, &mut out)?;
+                continue;
+            }
+        }
+        out.push_str(line);
+        i += line.len();
+    }
+    Ok(Expanded {
+        markdown: out,
+        html: expander.html,
+    })
+}
+
+/// `(char, length)` of the code fence `line` opens or closes, if any.
+fn fence_of(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_end();
+    let code = trimmed.trim_start_matches(' ');
+    if trimmed.len() - code.len() > 3 {
+        return None;
+    }
+    let c = code.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let n = code.len() - code.trim_start_matches(c).len();
+    (n >= 3).then_some((c, n))
+}
+
+struct Expander<'a> {
+    tera: &'a Tera,
+    file: &'a str,
+    first_line: usize,
+    markdown: &'a str,
+    /// Calls of each shortcode so far.
+    nth: HashMap<String, usize>,
+    /// Output of the HTML shortcodes, by placeholder.
+    html: Vec<String>,
+}
+
+impl Expander<'_> {
+    /// Expands the shortcodes of the line at `start`, and of the following ones
+    /// when a block shortcode spans them, into `out`. Returns the start of the
+    /// next line.
+    fn line(&mut self, start: usize, out: &mut String) -> anyhow::Result<usize> {
+        let markdown = self.markdown;
+        let mut i = start;
+        while i < markdown.len() {
+            let rest = &markdown[i..];
+            if rest.starts_with('\n') {
+                out.push('\n');
+                return Ok(i + 1);
+            }
+            if rest.starts_with('`') {
+                // Un code span va jusqu'à la prochaine suite d'autant de backticks
+                let n = rest.len() - rest.trim_start_matches('`').len();
+                let end = rest[n..]
+                    .match_indices(&rest[..n])
+                    .find(|(j, _)| !rest[n + j + n..].starts_with('`'))
+                    .map_or(n, |(j, _)| n + j + n);
+                out.push_str(&rest[..end]);
+                i += end;
+                continue;
+            }
+            let line = self.first_line + markdown[..i].matches('\n').count();
+            let file = self.file;
+            let at = || format!("{file}:{line}");
+            if let Some(call) = rest.strip_prefix("{{").and_then(|r| Call::parse(r, "}}")) {
+                let call = call.with_context(at)?;
+                let output = self.render(&call, None).with_context(at)?;
+                out.push_str(&self.insert(&call.name, output));
+                i += 2 + call.len;
+                continue;
+            }
+            if let Some(call) = rest.strip_prefix("{%").and_then(|r| Call::parse(r, "%}")) {
+                let call = call.with_context(at)?;
+                let body_start = i + 2 + call.len;
+                let (body_len, end_len) = block_end(&markdown[body_start..])
+                    .ok_or_else(|| anyhow!("shortcode `{}` has no `{{% end %}}`", call.name))
+                    .with_context(at)?;
+                let body = markdown[body_start..body_start + body_len]
+                    .trim_start_matches(['\r', '\n'])
+                    .trim_end();
+                let output = self.render(&call, Some(body)).with_context(at)?;
+                out.push_str(&self.insert(&call.name, output));
+                i = body_start + body_len + end_len;
+                continue;
+            }
+            let c = rest.chars().next().unwrap_or_default();
+            out.push(c);
+            i += c.len_utf8();
+        }
+        Ok(i)
+    }
+
+    fn render(&mut self, call: &Call, body: Option<&str>) -> anyhow::Result<String> {
+        let template = template(self.tera, &call.name);
+        if !self.tera.get_template_names().any(|n| n == template) {
+            bail!(
+                "unknown shortcode `{}` (no templates/{template}.html.tera)",
+                call.name
+            );
+        }
+        let nth = self.nth.entry(call.name.clone()).or_default();
+        *nth += 1;
+        let mut context = Context::new();
+        for (key, value) in &call.args {
+            context.insert(key, value);
+        }
+        context.insert("nth", nth);
+        if let Some(body) = body {
+            context.insert("body", body);
+        }
+        self.tera
+            .render(&template, &context)
+            .map_err(anyhow::Error::new)
+            .with_context(|| format!("failed to render shortcode `{}`", call.name))
+    }
+
+    /// Text to put into the markdown for the output of shortcode `name`: the output
+    /// itself for a markdown template, a placeholder for an HTML one.
+    fn insert(&mut self, name: &str, output: String) -> String {
+        if template(self.tera, name).ends_with(".md") {
+            return output;
+        }
+        self.html.push(output.trim().to_string());
+        placeholder(self.html.len() - 1)
+    }
+}
+
+/// `(body length, end tag length)` of the `{% end %}` closing a block shortcode.
+fn block_end(rest: &str) -> Option<(usize, usize)> {
+    let mut from = 0;
+    while let Some(j) = rest[from..].find("{%") {
+        let at = from + j;
+        let after = &rest[at + 2..];
+        if let Some(k) = after.find("%}")
+            && after[..k].trim() == "end"
+        {
+            return Some((at, 2 + k + 2));
+        }
+        from = at + 2;
+    }
+    None
+}
+
+/// Name of the template of shortcode `name`, the markdown one first.
+fn template(tera: &Tera, name: &str) -> String {
+    let markdown = format!("{SHORTCODES_DIR}/{name}.md");
+    if tera.get_template_names().any(|n| n == markdown) {
+        markdown
+    } else {
+        format!("{SHORTCODES_DIR}/{name}")
+    }
+}
+
+/// `name(key=value, …)` up to its closing delimiter.
+struct Call {
+    name: String,
+    args: Vec<(String, Value)>,
+    /// Length up to the end of the closing delimiter.
+    len: usize,
+}
+
+impl Call {
+    /// `None` when `source` does not start like a shortcode (`{{ name(`), an error
+    /// when it does but is malformed.
+    fn parse(source: &str, close: &str) -> Option<anyhow::Result<Self>> {
+        let mut cursor = Cursor { source, pos: 0 };
+        cursor.spaces();
+        let name = cursor.ident();
+        if name.is_empty() || !cursor.eat('(') {
+            return None;
+        }
+        Some(Self::args(cursor, name, close))
+    }
+
+    fn args(mut cursor: Cursor, name: String, close: &str) -> anyhow::Result<Self> {
+        let mut args = Vec::new();
+        loop {
+            cursor.spaces();
+            if cursor.eat(')') {
+                break;
+            }
+            let key = cursor.ident();
+            cursor.spaces();
+            if key.is_empty() || !cursor.eat('=') {
+                bail!("shortcode `{name}`: expected `key=value` arguments");
+            }
+            cursor.spaces();
+            let value = cursor
+                .value()
+                .with_context(|| format!("shortcode `{name}`: invalid value of `{key}`"))?;
+            args.push((key, value));
+            cursor.spaces();
+            if !cursor.eat(',') && !cursor.rest().starts_with(')') {
+                bail!("shortcode `{name}`: expected `,` or `)`");
+            }
+        }
+        cursor.spaces();
+        if !cursor.rest().starts_with(close) {
+            bail!("shortcode `{name}`: expected `{close}`");
+        }
+        Ok(Self {
+            name,
+            args,
+            len: cursor.pos + close.len(),
+        })
+    }
+}
+
+struct Cursor<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.pos..]
+    }
+
+    fn spaces(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.rest().starts_with(c);
+        if found {
+            self.pos += c.len_utf8();
+        }
+        found
+    }
+
+    fn ident(&mut self) -> String {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        let ident = rest[..len].to_string();
+        self.pos += len;
+        ident
+    }
+
+    /// A string (`"…"`, `'…'` or `` `…` ``), number, boolean or array of them.
+    fn value(&mut self) -> anyhow::Result<Value> {
+        let source = self.source;
+        let rest = &source[self.pos..];
+        if let Some(quote) = rest
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\'' | '`'))
+        {
+            let end = rest[1..]
+                .find(quote)
+                .ok_or_else(|| anyhow!("unterminated string"))?;
+            self.pos += end + 2;
+            return Ok(Value::String(rest[1..=end].to_string()));
+        }
+        if self.eat('[') {
+            let mut items = Vec::new();
+            loop {
+                self.spaces();
+                if self.eat(']') {
+                    return Ok(Value::Array(items));
+                }
+                items.push(self.value()?);
+                self.spaces();
+                if !self.eat(',') && !self.rest().starts_with(']') {
+                    bail!("expected `,` or `]`");
+                }
+            }
+        }
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && !matches!(c, '.' | '-' | '+'))
+            .unwrap_or(rest.len());
+        let word = &rest[..len];
+        let value = match word {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => serde_json::from_str::<serde_json::Number>(word)
+                .map(Value::Number)
+                .map_err(|_| {
+                    anyhow!("expected a string, number, boolean or array, found `{word}`")
+                })?,
+        };
+        self.pos += len;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, render, testing};
+
+    fn engine(name: &str) -> Tera {
+        let dir = testing::dir(name);
+        testing::file(
+            &dir,
+            "shortcodes/youtube.html.tera",
+            r#"<iframe src="https://youtu.be/{{ id }}" data-nth="{{ nth }}"></iframe>"#,
+        );
+        testing::file(
+            &dir,
+            "shortcodes/callout.html.tera",
+            r#"<aside class="{{ kind }}">{{ body }}</aside>"#,
+        );
+        testing::file(&dir, "shortcodes/note.md.tera", "> **{{ text }}**");
+        render::engine(&dir, &Config::default()).unwrap()
+    }
+
+    fn expanded(tera: &Tera, markdown: &str) -> String {
+        let expanded = expand(tera, "content/a.md", 1, markdown).unwrap();
+        expanded.restore(&expanded.markdown)
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        let call = Call::parse(
+            r#" youtube(id="a b", start=30, ratio=1.5, loop=true, tags=['x', `y`]) }}"#,
+            "}}",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(call.name, "youtube");
+        let args: Vec<String> = call.args.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        assert_eq!(
+            args,
+            [
+                r#"id="a b""#,
+                "start=30",
+                "ratio=1.5",
+                "loop=true",
+                r#"tags=["x","y"]"#
+            ]
+        );
+        assert!(Call::parse(" not a call }}", "}}").is_none());
+        assert!(Call::parse(" page.title }}", "}}").is_none());
+        for bad in [
+            r#"x(id="a) }}"#,
+            "x(id) }}",
+            "x(id=nope) }}",
+            "x(a=1 b=2) }}",
+        ] {
+            assert!(Call::parse(bad, "}}").unwrap().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn shortcode_arguments_are_escaped() {
+        let tera = engine("shortcodes-escape");
+        let html = expanded(&tera, "{{ youtube(id='\"><script>alert(1)</script>') }}\n");
+        assert!(!html.contains("<script>"), "{html}");
+        assert!(html.contains("&quot;&gt;&lt;script&gt;"), "{html}");
+    }
+
+    #[test]
+    fn blocks_get_their_body_and_calls_are_numbered() {
+        let tera = engine("shortcodes-blocks");
+        let html = expanded(
+            &tera,
+            "{{ youtube(id=\"a\") }}\n{% callout(kind=\"tip\") %}\nRead *me*\n{% end %}\n{{ youtube(id=\"b\") }}\n",
+        );
+        assert_eq!(
+            html,
+            "<iframe src=\"https://youtu.be/a\" data-nth=\"1\"></iframe>\n<aside class=\"tip\">Read *me*</aside>\n<iframe src=\"https://youtu.be/b\" data-nth=\"2\"></iframe>\n"
+        );
+        // Un gabarit markdown est inséré tel quel, avant le rendu
+        assert_eq!(expanded(&tera, "{{ note(text=\"Hi\") }}"), "> **Hi**");
+    }
+
+    #[test]
+    fn code_is_left_alone() {
+        let tera = engine("shortcodes-code");
+        let source = "`{{ youtube(id=\"a\") }}`\n\n```\n{{ youtube(id=\"a\") }}\n```\n";
+        assert_eq!(expanded(&tera, source), source);
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let tera = engine("shortcodes-errors");
+        let err = |markdown| {
+            format!(
+                "{:#}",
+                expand(&tera, "content/a.md", 3, markdown).err().unwrap()
+            )
+        };
+        assert!(err("x\n{{ nope() }}").starts_with("content/a.md:4: unknown shortcode `nope`"));
+        assert!(err("{% callout(kind=\"a\") %}\nbody\n").contains("has no `{% end %}`"));
+    }
+}
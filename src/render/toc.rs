@@ -0,0 +1,188 @@
+//! Heading IDs, anchor links and table of contents of rendered markdown.
+//!
+//! IDs are built by comrak's anchorizer, the GitHub way (`## Hello, World!` →
+//! `hello-world`, then `hello-world-1` for the next one), so they stay stable
+//! as long as the heading text does.
+
+use crate::{config::Config, render::filters};
+use comrak::{
+    Anchorizer,
+    adapters::{HeadingAdapter, HeadingMeta},
+    nodes::Sourcepos,
+};
+use rocket_dyn_templates::tera;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    sync::Mutex,
+};
+
+/// A heading of the table of contents and the headings nested under it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub title: String,
+    pub children: Vec<TocEntry>,
+}
+
+/// Heading adapter giving every heading an `id`, and an anchor link with `anchors`.
+/// The headings met while rendering are kept for [`Headings::toc`].
+#[derive(Default)]
+pub struct Headings {
+    anchors: bool,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    anchorizer: Anchorizer,
+    /// `(level, id, title)` in document order.
+    headings: Vec<(u8, String, String)>,
+}
+
+impl Headings {
+    pub fn new(anchors: bool) -> Self {
+        Self {
+            anchors,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Nested table of contents of the headings from level `min` to `max`.
+    pub fn toc(&self, min: u8, max: u8) -> Vec<TocEntry> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let flat = state
+            .headings
+            .iter()
+            .filter(|(level, _, _)| (min..=max).contains(level))
+            .map(|(level, id, title)| TocEntry {
+                level: *level,
+                id: id.clone(),
+                title: title.clone(),
+                children: Vec::new(),
+            });
+        nest(flat)
+    }
+}
+
+impl HeadingAdapter for Headings {
+    fn enter(
+        &self,
+        output: &mut dyn Write,
+        heading: &HeadingMeta,
+        _sourcepos: Option<Sourcepos>,
+    ) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let id = state.anchorizer.anchorize(heading.content.clone());
+        write!(output, "<h{} id=\"{id}\">", heading.level)?;
+        state
+            .headings
+            .push((heading.level, id, heading.content.trim().to_string()));
+        Ok(())
+    }
+
+    fn exit(&self, output: &mut dyn Write, heading: &HeadingMeta) -> io::Result<()> {
+        if self.anchors {
+            let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((_, id, _)) = state.headings.last() {
+                write!(
+                    output,
+                    " <a class=\"anchor\" href=\"#{id}\" aria-hidden=\"true\">#</a>"
+                )?;
+            }
+        }
+        writeln!(output, "</h{}>", heading.level)
+    }
+}
+
+/// Nests a flat list of headings: each one goes under the closest previous heading
+/// of a lower level.
+fn nest(flat: impl Iterator<Item = TocEntry>) -> Vec<TocEntry> {
+    let mut root: Vec<TocEntry> = Vec::new();
+    for entry in flat {
+        let mut siblings = &mut root;
+        while siblings.last().is_some_and(|last| last.level < entry.level) {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+        siblings.push(entry);
+    }
+    root
+}
+
+/// `{% set doc = markdown_toc(text=post.body, min_depth=2, max_depth=3) %}`:
+/// `{html, toc}` of a markdown text, the depths defaulting to `[markdown]`.
+pub fn markdown_toc(
+    config: &Config,
+    args: &HashMap<String, tera::Value>,
+) -> tera::Result<tera::Value> {
+    let text = args
+        .get("text")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let depth = |k: &str, default: u8| {
+        args.get(k)
+            .and_then(|v| v.as_u64())
+            .map_or(default, |d| d as u8)
+    };
+    let (html, toc) = filters::render_markdown_toc(
+        config,
+        text,
+        depth("min_depth", config.markdown.toc_min_depth),
+        depth("max_depth", config.markdown.toc_max_depth),
+    );
+    let html = filters::typography(config, &filters::replace_emoji(config, &html), None);
+    Ok(tera::to_value(serde_json::json!({ "html": html, "toc": toc })).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toc_of(markdown: &str, min: u8, max: u8) -> (String, Vec<TocEntry>) {
+        filters::render_markdown_toc(&Config::default(), markdown, min, max)
+    }
+
+    fn titles(entries: &[TocEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|e| format!("{} {}{:?}", e.id, e.title, titles(&e.children)))
+            .collect()
+    }
+
+    #[test]
+    fn headings_get_github_ids() {
+        let (html, _) = toc_of("# Hello, World!\n\n## Hello, World!\n", 1, 6);
+        assert!(html.contains(r#"<h1 id="hello-world">"#), "{html}");
+        assert!(html.contains(r#"<h2 id="hello-world-1">"#), "{html}");
+    }
+
+    #[test]
+    fn toc_nests_the_headings_in_range() {
+        let (_, toc) = toc_of(
+            "# Title\n## One\n### One.a\n#### Deep\n## Two\n### Two.a\n",
+            2,
+            3,
+        );
+        assert_eq!(
+            titles(&toc),
+            ["one One[\"onea One.a[]\"]", "two Two[\"twoa Two.a[]\"]"]
+        );
+
+        // Un titre plus profond sans parent remonte au premier niveau
+        let (_, toc) = toc_of("### Deep\n## Top\n", 2, 3);
+        assert_eq!(titles(&toc), ["deep Deep[]", "top Top[]"]);
+    }
+
+    #[test]
+    fn anchor_links_follow_the_config() {
+        let mut config = Config::default();
+        config.markdown.anchors = true;
+        let (html, _) = filters::render_markdown_toc(&config, "## Hi\n", 1, 6);
+        assert!(html.contains(r##"<a class="anchor" href="#hi" aria-hidden="true">#</a></h2>"##));
+        config.markdown.anchors = false;
+        let (html, _) = filters::render_markdown_toc(&config, "## Hi\n", 1, 6);
+        assert!(!html.contains("anchor"), "{html}");
+    }
+}
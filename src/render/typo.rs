@@ -0,0 +1,340 @@
+//! Typographic fixes of rendered HTML, by language.
+//!
+//! Both rule sets turn `'` between letters into `’` and `...` into `…`. French
+//! also puts a narrow no-break space before `; : ! ?` and `%` and inside
+//! `« guillemets »`, and groups the thousands of numbers with it (`10 000`);
+//! English removes the spaces before `; : ! ?`. Only the text is changed: tags,
+//! their attributes, the content of `<code>`, `<pre>`, `<kbd>`, `<samp>`,
+//! `<script>` and `<style>`, URLs and HTML entities are left as they are.
+
+use crate::render::{
+    excerpt::{INLINE_ELEMENTS, tag_name},
+    locale::Lang,
+};
+
+/// No-break space.
+const NBSP: char = '\u{a0}';
+/// Narrow no-break space.
+const NNBSP: char = '\u{202f}';
+
+/// Elements whose text is left alone.
+const RAW_ELEMENTS: &[&str] = &["code", "pre", "kbd", "samp", "script", "style"];
+
+/// `html` with the typographic rules of `lang` applied to its text.
+pub fn apply(html: &str, lang: Lang) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 16);
+    let mut raw = 0usize;
+    // Dernier caractère du texte, par-delà les balises en ligne (`<em>l</em>'a`)
+    let mut prev = None;
+    let mut rest = html;
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            let tag = &rest[..end];
+            let name = tag_name(tag);
+            if RAW_ELEMENTS.contains(&name.as_str()) {
+                if tag.starts_with("</") {
+                    raw = raw.saturating_sub(1);
+                } else if !tag.ends_with("/>") {
+                    raw += 1;
+                }
+            }
+            if !tag.starts_with("<!") && !INLINE_ELEMENTS.contains(&name.as_str()) {
+                prev = None;
+            }
+            out.push_str(tag);
+            rest = &rest[end..];
+            continue;
+        }
+        let end = rest.find('<').unwrap_or(rest.len());
+        let node = &rest[..end];
+        if raw == 0 {
+            text(node, lang, &mut prev, &mut out);
+        } else {
+            out.push_str(node);
+            prev = node.chars().next_back().or(prev);
+        }
+        rest = &rest[end..];
+    }
+    out
+}
+
+/// Applies the rules to a text node following the text character `prev`, its URLs
+/// copied as they are.
+fn text(text: &str, lang: Lang, prev: &mut Option<char>, out: &mut String) {
+    let mut rest = text;
+    while let Some(start) = url_start(rest) {
+        rules(&rest[..start], lang, prev, out);
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |i| start + i);
+        out.push_str(&rest[start..end]);
+        *prev = rest[..end].chars().next_back();
+        rest = &rest[end..];
+    }
+    rules(rest, lang, prev, out);
+}
+
+/// Start of the first URL of `text`: a word starting with a scheme or `www.`.
+fn url_start(text: &str) -> Option<usize> {
+    text.match_indices("://")
+        .map(|(i, _)| {
+            text[..i]
+                .rfind(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+                .map_or(0, |j| j + 1)
+        })
+        .chain(text.match_indices("www.").map(|(i, _)| i))
+        .filter(|&i| i == 0 || text[..i].ends_with(|c: char| c.is_whitespace() || c == '('))
+        .min()
+}
+
+/// A character of the text, or an HTML entity kept whole.
+#[derive(Clone, Copy)]
+enum Unit<'a> {
+    Char(char),
+    Entity(&'a str),
+}
+
+impl Unit<'_> {
+    fn char(self) -> Option<char> {
+        match self {
+            Unit::Char(c) => Some(c),
+            Unit::Entity(_) => None,
+        }
+    }
+}
+
+fn units(text: &str) -> Vec<Unit<'_>> {
+    let mut units = Vec::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let entity = (c == '&').then(|| rest.find(';')).flatten().filter(|&end| {
+            end > 1
+                && rest[1..end]
+                    .chars()
+                    .all(|c| c == '#' || c.is_ascii_alphanumeric())
+        });
+        match entity {
+            Some(end) => {
+                units.push(Unit::Entity(&rest[..=end]));
+                rest = &rest[end + 1..];
+            }
+            None => {
+                units.push(Unit::Char(c));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    units
+}
+
+/// Applies the rules to `text`, `last` being the text character before it, which
+/// is updated. Only the spaces of `text` itself are removed from `out`, never a tag.
+fn rules(text: &str, lang: Lang, last: &mut Option<char>, out: &mut String) {
+    let units = units(text);
+    let at = |i: usize| units.get(i).and_then(|u| u.char());
+    let start = out.len();
+    let mut i = 0;
+    while i < units.len() {
+        let c = match units[i] {
+            Unit::Entity(entity) => {
+                out.push_str(entity);
+                i += 1;
+                continue;
+            }
+            Unit::Char(c) => c,
+        };
+        let prev = if out.len() > start {
+            out.chars().next_back()
+        } else {
+            *last
+        };
+        // Espace de ce texte juste avant `c`, qui peut être retirée
+        let spaced = out.len() > start && out.ends_with([' ', NBSP]);
+        let next = at(i + 1);
+        // Fin de phrase : rien ou une espace après la ponctuation
+        let ends = next.is_none_or(|n| n.is_whitespace() || matches!(n, '»' | ')' | '"'));
+        match c {
+            '\'' if prev.is_some_and(char::is_alphabetic)
+                && next.is_some_and(char::is_alphabetic) =>
+            {
+                out.push('’');
+            }
+            '.' if at(i + 1) == Some('.') && at(i + 2) == Some('.') => {
+                out.push('…');
+                i += 2;
+            }
+            ';' | ':' | '!' | '?'
+                if ends
+                    || matches!(c, '!' | '?') && next.is_some_and(|n| matches!(n, '!' | '?')) =>
+            {
+                match lang {
+                    Lang::Fr => {
+                        if spaced {
+                            out.pop();
+                        }
+                        let word = prev.is_some_and(|p| {
+                            p.is_alphanumeric() || matches!(p, '»' | ')' | '’' | '…')
+                        });
+                        if spaced || word {
+                            out.push(NNBSP);
+                        }
+                    }
+                    Lang::En => {
+                        while out.len() > start && out.ends_with([' ', NBSP, NNBSP]) {
+                            out.pop();
+                        }
+                    }
+                }
+                out.push(c);
+            }
+            '«' if lang == Lang::Fr => {
+                out.push('«');
+                out.push(NNBSP);
+                if next.is_some_and(|n| matches!(n, ' ' | NBSP | NNBSP)) {
+                    i += 1;
+                }
+            }
+            '»' if lang == Lang::Fr => {
+                if spaced {
+                    out.pop();
+                }
+                let before = if out.len() > start {
+                    out.chars().next_back()
+                } else {
+                    *last
+                };
+                if before != Some(NNBSP) {
+                    out.push(NNBSP);
+                }
+                out.push('»');
+            }
+            ' ' if lang == Lang::Fr && prev.is_some_and(|p| p.is_ascii_digit()) => {
+                // Séparateur de milliers (un groupe de 3 chiffres, pas plus) ou `%`
+                let group = (1..=3).all(|k| at(i + k).is_some_and(|d| d.is_ascii_digit()))
+                    && !at(i + 4).is_some_and(|d| d.is_ascii_digit());
+                out.push(if group || next == Some('%') {
+                    NNBSP
+                } else {
+                    ' '
+                });
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+    if out.len() > start {
+        *last = out.chars().next_back();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fr(html: &str) -> String {
+        apply(html, Lang::Fr).replace(NNBSP, "_").replace(NBSP, "~")
+    }
+
+    fn en(html: &str) -> String {
+        apply(html, Lang::En).replace(NNBSP, "_").replace(NBSP, "~")
+    }
+
+    #[test]
+    fn french_spacing() {
+        assert_eq!(
+            fr("<p>Quoi ? Oui ! Non ; voici : 5 %</p>"),
+            "<p>Quoi_? Oui_! Non_; voici_: 5_%</p>"
+        );
+        assert_eq!(fr("<p>Quoi?! Oui!</p>"), "<p>Quoi_?! Oui_!</p>");
+        assert_eq!(fr("<p>Quoi&nbsp;?</p>"), "<p>Quoi&nbsp;?</p>");
+        assert_eq!(
+            fr("<p>Quoi~?</p>".replace('~', "\u{a0}").as_str()),
+            "<p>Quoi_?</p>"
+        );
+        // Pas de ponctuation isolée ni d'heure ou de rapport
+        assert_eq!(fr("<p>12:30, a:b</p>"), "<p>12:30, a:b</p>");
+        assert_eq!(
+            fr("<p>C'est l'été... enfin</p>"),
+            "<p>C’est l’été… enfin</p>"
+        );
+    }
+
+    #[test]
+    fn guillemets() {
+        assert_eq!(fr("<p>« Oui »</p>"), "<p>«_Oui_»</p>");
+        assert_eq!(fr("<p>«Oui»</p>"), "<p>«_Oui_»</p>");
+        assert_eq!(en("<p>«Oui»</p>"), "<p>«Oui»</p>");
+    }
+
+    #[test]
+    fn thousands_grouping() {
+        assert_eq!(
+            fr("<p>10 000 et 1 234 567 €</p>"),
+            "<p>10_000 et 1_234_567 €</p>"
+        );
+        assert_eq!(
+            fr("<p>en 2024 2025 fois, 12 3456</p>"),
+            "<p>en 2024 2025 fois, 12 3456</p>"
+        );
+        assert_eq!(en("<p>10 000</p>"), "<p>10 000</p>");
+    }
+
+    #[test]
+    fn english_removes_spaces_before_punctuation() {
+        assert_eq!(
+            en("<p>What ? Yes ! It's... fine :</p>"),
+            "<p>What? Yes! It’s… fine:</p>"
+        );
+    }
+
+    #[test]
+    fn rules_see_the_text_across_tags() {
+        // `>` d'une balise n'est pas un caractère du texte
+        assert_eq!(fr("<p><em>Oui</em> ?</p>"), "<p><em>Oui</em>_?</p>");
+        assert_eq!(fr("<p><em>Oui</em>!</p>"), "<p><em>Oui</em>_!</p>");
+        assert_eq!(
+            fr("<p><a href=\"/\">l</a>'été</p>"),
+            "<p><a href=\"/\">l</a>’été</p>"
+        );
+        assert_eq!(fr("<p>Oui <em>?</em></p>"), "<p>Oui <em>?</em></p>");
+        assert_eq!(en("<p>Why <b>?</b></p>"), "<p>Why <b>?</b></p>");
+        assert_eq!(
+            fr("<p>a</p><p>'b'</p><p>!</p>"),
+            "<p>a</p><p>'b'</p><p>!</p>"
+        );
+        assert_eq!(fr("<p>10</p><p> 000</p>"), "<p>10</p><p> 000</p>");
+    }
+
+    #[test]
+    fn code_urls_and_entities_are_left_alone() {
+        let code =
+            "<pre><code>a ? b... 'c' 10 000</code></pre><p><code>x ?</code> <kbd>y !</kbd></p>";
+        assert_eq!(fr(code), code);
+        assert_eq!(
+            fr("<p>Voir https://ex.com/a?b=1... ou www.ex.com/x:y ?</p>"),
+            "<p>Voir https://ex.com/a?b=1... ou www.ex.com/x:y_?</p>"
+        );
+        assert_eq!(
+            fr("<p>A &amp; B ? &#8217;x&hellip;</p>"),
+            "<p>A &amp; B_? &#8217;x&hellip;</p>"
+        );
+        assert_eq!(
+            fr("<p title=\"Quoi ?\">Quoi ?</p>"),
+            "<p title=\"Quoi ?\">Quoi_?</p>"
+        );
+    }
+
+    #[test]
+    fn rules_are_idempotent() {
+        for html in [
+            "<p>« Oui » ? 10 000 l'été... 5 %</p>",
+            "<p><em>Oui</em> ! C'est <a href=\"/\">ça</a> ?</p>",
+        ] {
+            for lang in [Lang::Fr, Lang::En] {
+                let once = apply(html, lang);
+                assert_eq!(apply(&once, lang), once, "{html}");
+            }
+        }
+    }
+}
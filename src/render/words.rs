@@ -0,0 +1,174 @@
+//! Word count and reading time of rendered pages.
+//!
+//! Words are counted in the text of the HTML, code blocks excluded. Chinese and
+//! Japanese are not written with spaces, so each of their characters counts as a
+//! word and is read at `[markdown] cjk_per_minute` rather than `words_per_minute`.
+
+use crate::{
+    config::MarkdownConfig,
+    render::excerpt::{INLINE_ELEMENTS, tag_name},
+};
+use serde::Serialize;
+
+/// Elements whose text is not read.
+const SKIPPED_ELEMENTS: &[&str] = &["pre", "script", "style"];
+
+/// Words of a text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Words {
+    /// Words written with spaces between them.
+    pub spaced: usize,
+    /// CJK characters.
+    pub cjk: usize,
+}
+
+impl Words {
+    /// Words of plain `text`.
+    pub fn of_text(text: &str) -> Self {
+        let mut words = Self::default();
+        let mut in_word = false;
+        for c in text.chars() {
+            if is_cjk(c) {
+                words.cjk += 1;
+                in_word = false;
+            } else if c.is_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                // La ponctuation seule n'est pas un mot
+                in_word = c.is_alphanumeric();
+                words.spaced += usize::from(in_word);
+            }
+        }
+        words
+    }
+
+    /// Words of the text of `html`, without its code blocks.
+    pub fn of_html(html: &str) -> Self {
+        let mut text = String::with_capacity(html.len());
+        let mut skipped = 0usize;
+        let mut rest = html;
+        while !rest.is_empty() {
+            if rest.starts_with('<') {
+                let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+                let tag = &rest[..end];
+                let name = tag_name(tag);
+                if SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    if tag.starts_with("</") {
+                        skipped = skipped.saturating_sub(1);
+                    } else {
+                        skipped += 1;
+                    }
+                }
+                // Hors des éléments en ligne (`a<em>b</em>`), une balise sépare les mots
+                if !tag.starts_with("<!") && !INLINE_ELEMENTS.contains(&name.as_str()) {
+                    text.push(' ');
+                }
+                rest = &rest[end..];
+                continue;
+            }
+            let end = rest.find('<').unwrap_or(rest.len());
+            if skipped == 0 {
+                push_unescaped(&mut text, &rest[..end]);
+            }
+            rest = &rest[end..];
+        }
+        Self::of_text(&text)
+    }
+
+    pub fn total(&self) -> usize {
+        self.spaced + self.cjk
+    }
+
+    /// Minutes it takes to read the words, rounded up; 0 without words.
+    pub fn reading_time(&self, config: &MarkdownConfig) -> usize {
+        let minutes = self.spaced as f64 / config.words_per_minute.max(1) as f64
+            + self.cjk as f64 / config.cjk_per_minute.max(1) as f64;
+        minutes.ceil() as usize
+    }
+}
+
+/// Pushes `html` text with its entities as `&`, or as a space for `&nbsp;`, so
+/// that `&amp;` is not taken for a word.
+fn push_unescaped(text: &mut String, html: &str) {
+    let mut rest = html;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find(';').filter(|&end| {
+            end > 0
+                && after[..end]
+                    .chars()
+                    .all(|c| c == '#' || c.is_ascii_alphanumeric())
+        }) {
+            Some(end) => {
+                let space = matches!(&after[..end], "nbsp" | "#160" | "#xa0" | "#xA0");
+                text.push(if space { ' ' } else { '&' });
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('&');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+}
+
+/// Han and kana; Hangul is written with spaces.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'   // hiragana, katakana
+        | '\u{3400}'..='\u{4dbf}' // extension A
+        | '\u{4e00}'..='\u{9fff}' // idéogrammes unifiés
+        | '\u{f900}'..='\u{faff}' // compatibilité
+        | '\u{ff66}'..='\u{ff9f}' // katakana demi-chasse
+        | '\u{20000}'..='\u{2ebef}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(spaced: usize, cjk: usize) -> Words {
+        Words { spaced, cjk }
+    }
+
+    #[test]
+    fn words_of_text() {
+        assert_eq!(Words::of_text("  one two\tthree\n"), words(3, 0));
+        assert_eq!(Words::of_text("l'été — déjà !"), words(2, 0));
+        assert_eq!(Words::of_text("日本語 and 中文"), words(1, 5));
+        assert_eq!(Words::of_text("한국어 문장"), words(2, 0));
+        assert_eq!(Words::of_text(""), words(0, 0));
+    }
+
+    #[test]
+    fn words_of_html_skip_code_blocks_and_entities() {
+        let html = "<h1>Title</h1><p>One<br>two <em>thr</em>ee &amp; four&nbsp;five</p>\
+                    <pre><code>let x = 1;</code></pre><p>six <code>inline</code></p>\
+                    <script>var a = 1;</script><!-- a comment -->";
+        assert_eq!(Words::of_html(html), words(8, 0));
+        assert_eq!(Words::of_html("<p>a<b>b</b>c</p><p>d</p>").total(), 2);
+    }
+
+    #[test]
+    fn reading_time_is_rounded_up() {
+        let config = MarkdownConfig {
+            words_per_minute: 200,
+            cjk_per_minute: 500,
+            ..Default::default()
+        };
+        assert_eq!(words(0, 0).reading_time(&config), 0);
+        assert_eq!(words(1, 0).reading_time(&config), 1);
+        assert_eq!(words(200, 0).reading_time(&config), 1);
+        assert_eq!(words(201, 0).reading_time(&config), 2);
+        assert_eq!(words(100, 250).reading_time(&config), 1);
+        assert_eq!(words(100, 251).reading_time(&config), 2);
+        let config = MarkdownConfig {
+            words_per_minute: 0,
+            ..config
+        };
+        assert_eq!(words(3, 0).reading_time(&config), 3);
+    }
+}
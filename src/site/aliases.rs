@@ -0,0 +1,335 @@
+//! Redirects of the former URLs listed in the `aliases` of a page.
+//!
+//! Every alias ending with `/` or `.html` gets a meta-refresh stub, so redirects
+//! work on any static host, and the whole list is also written as a Netlify /
+//! Cloudflare Pages `_redirects` file and as an nginx map (`redirects.map`), to be
+//! used with:
+//!
+//! ```nginx
+//! map $uri $redirect_uri { include /path/to/dist/redirects.map; }
+//! if ($redirect_uri) { return 301 $redirect_uri; }
+//! ```
+//!
+//! Other file names, like `old.php`, get no stub: a static host would serve it with
+//! their own content type, so only the server redirects them.
+//!
+//! An alias claimed by two pages, going up with `..`, or landing on a generated
+//! file (a page, a feed, the sitemap…) or under `/public/` or `/kit/`, fails the
+//! build.
+
+use crate::{
+    render::helpers::escape,
+    site::{Output, Site, page_file},
+};
+use anyhow::{Context, bail};
+use std::{collections::BTreeMap, fmt::Write as _, path::PathBuf};
+
+pub const REDIRECTS_FILE: &str = "_redirects";
+pub const NGINX_MAP_FILE: &str = "redirects.map";
+
+/// Directories of the copied assets, which aliases may not point into.
+const RESERVED: &[&str] = &["/public/", "/kit/"];
+
+/// `(alias, target)` URL paths of every published page, sorted by alias.
+pub fn redirects(site: &Site) -> anyhow::Result<Vec<(String, String)>> {
+    let mut redirects: BTreeMap<String, String> = BTreeMap::new();
+    for page in site.pages() {
+        for alias in &page.aliases {
+            let alias = normalize(alias).with_context(|| format!("alias of {}", page.path))?;
+            if alias == page.path {
+                continue;
+            }
+            if let Some(dir) = RESERVED.iter().find(|d| alias.starts_with(*d)) {
+                bail!("alias {alias} of {} is inside {dir}", page.path);
+            }
+            match redirects.get(&alias) {
+                Some(target) if *target != page.path => {
+                    let (a, b) = (target.min(&page.path), target.max(&page.path));
+                    bail!("alias {alias} is claimed by both {a} and {b}")
+                }
+                _ => {
+                    redirects.insert(alias, page.path.clone());
+                }
+            }
+        }
+    }
+    Ok(redirects.into_iter().collect())
+}
+
+/// `old/post` → `/old/post/`; paths ending with a file name are left as is. An
+/// alias with a `.` or `..` segment or a `\\` could land outside the output
+/// directory and is an error.
+pub fn normalize(alias: &str) -> anyhow::Result<String> {
+    let alias = alias.trim();
+    if alias.contains('\\') || alias.split('/').any(|s| s == "." || s == "..") {
+        bail!("invalid alias {alias}: it must be a path under the site root");
+    }
+    let mut path = format!("/{}", alias.trim_start_matches('/'));
+    let last = path.rsplit('/').next().unwrap_or("");
+    if !path.ends_with('/') && !last.contains('.') {
+        path.push('/');
+    }
+    Ok(path)
+}
+
+/// Output file of the stub of `alias`: none for a file name other than `.html`.
+pub fn stub_file(alias: &str) -> Option<PathBuf> {
+    let last = alias.rsplit('/').next().unwrap_or_default();
+    if last.is_empty() {
+        Some(page_file(alias))
+    } else if last.ends_with(".html") || last.ends_with(".htm") {
+        Some(PathBuf::from(alias.trim_start_matches('/')))
+    } else {
+        None
+    }
+}
+
+/// Writes the redirect stubs, `_redirects` and `redirects.map` into `out`, which
+/// already holds every other generated file, returning the number of written files.
+/// An alias that would overwrite one of them is an error.
+pub fn write_all(site: &Site, output: &mut Output) -> anyhow::Result<usize> {
+    let redirects = redirects(site)?;
+    if redirects.is_empty() {
+        return Ok(0);
+    }
+    let mut written = 0;
+    let mut netlify = String::new();
+    let mut nginx = String::new();
+    for (alias, target) in &redirects {
+        let file = stub_file(alias);
+        // Même sans page de redirection, un alias ne masque pas un fichier généré
+        let taken = file
+            .clone()
+            .unwrap_or_else(|| alias.trim_start_matches('/').into());
+        if let Some(source) = output.source(&taken) {
+            bail!("alias {alias} of {target} would overwrite {source}");
+        }
+        if let Some(file) = file {
+            output.write(
+                file,
+                &format!("alias {alias} of {target}"),
+                &stub(&site.config.url(target)),
+            )?;
+            written += 1;
+        }
+        let _ = writeln!(netlify, "{alias} {target} 301");
+        let _ = writeln!(nginx, "{alias} {target};");
+    }
+    output.write(REDIRECTS_FILE, "redirects", &netlify)?;
+    output.write(NGINX_MAP_FILE, "redirects", &nginx)?;
+    Ok(written + 2)
+}
+
+/// HTML page redirecting to `url` at once, kept out of search engines.
+pub fn stub(url: &str) -> String {
+    let url = escape(url);
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Redirecting…</title>
+<link rel="canonical" href="{url}">
+<meta name="robots" content="noindex">
+<meta http-equiv="refresh" content="0; url={url}">
+</head>
+<body><a href="{url}">{url}</a></body>
+</html>
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const CONFIG: &str = "[site]\nbase_url = \"https://example.com\"\n";
+
+    #[test]
+    fn aliases_are_normalized() {
+        let normalize = |alias| normalize(alias).unwrap();
+        assert_eq!(normalize("old/post"), "/old/post/");
+        assert_eq!(normalize(" /old/post/ "), "/old/post/");
+        assert_eq!(normalize("v1.2/post"), "/v1.2/post/");
+        assert_eq!(normalize("old.php"), "/old.php");
+        assert_eq!(normalize("..old/"), "/..old/");
+    }
+
+    #[test]
+    fn aliases_stay_under_the_output_directory() {
+        for alias in [
+            "../../escaped/",
+            "/a/../../b/",
+            "./a",
+            "a/..",
+            "..\\x",
+            "c:\\x",
+        ] {
+            assert!(super::normalize(alias).is_err(), "{alias}");
+        }
+        let site = testing::site(
+            "aliases-escape",
+            &[
+                ("rey.toml", CONFIG),
+                ("content/a.md", "+++\naliases = [\"../../escaped/\"]\n+++\n"),
+            ],
+        );
+        let err = format!("{:#}", redirects(&site).unwrap_err());
+        assert!(
+            err.contains("alias of /a/: invalid alias ../../escaped/"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn only_directories_and_html_files_get_a_stub() {
+        assert_eq!(
+            stub_file("/old/post/"),
+            Some(PathBuf::from("old/post/index.html"))
+        );
+        assert_eq!(stub_file("/old.html"), Some(PathBuf::from("old.html")));
+        assert_eq!(stub_file("/a/old.htm"), Some(PathBuf::from("a/old.htm")));
+        assert_eq!(stub_file("/old.php"), None);
+        assert_eq!(stub_file("/feed.xml"), None);
+    }
+
+    #[test]
+    fn an_alias_belongs_to_one_page() {
+        let site = testing::site(
+            "aliases-claimed",
+            &[
+                ("rey.toml", CONFIG),
+                ("content/a.md", "+++\naliases = [\"old\", \"/a/\"]\n+++\n"),
+                ("content/b.md", "+++\naliases = [\"/old/\"]\n+++\n"),
+            ],
+        );
+        let err = redirects(&site).unwrap_err().to_string();
+        assert_eq!(err, "alias /old/ is claimed by both /a/ and /b/");
+
+        let site = testing::site(
+            "aliases-once",
+            &[
+                ("rey.toml", CONFIG),
+                (
+                    "content/a.md",
+                    "+++\naliases = [\"old\", \"/old/\", \"/a/\"]\n+++\n",
+                ),
+            ],
+        );
+        assert_eq!(
+            redirects(&site).unwrap(),
+            [("/old/".to_string(), "/a/".to_string())]
+        );
+    }
+
+    #[test]
+    fn aliases_do_not_overwrite_generated_files() {
+        for alias in ["rss.xml", "/blog/", "public/x/"] {
+            let site = testing::site(
+                "aliases-generated",
+                &[
+                    ("rey.toml", CONFIG),
+                    (
+                        "content/a.md",
+                        &format!("+++\naliases = [\"{alias}\"]\n+++\n"),
+                    ),
+                ],
+            );
+            let mut output = Output::new(&testing::dir("aliases-generated-out"));
+            output.write("rss.xml", "feed /", "").unwrap();
+            output
+                .write("blog/index.html", "content/blog/_index.md", "")
+                .unwrap();
+            assert!(write_all(&site, &mut output).is_err(), "{alias}");
+        }
+    }
+
+    #[test]
+    fn permalinks_fill_their_placeholders() {
+        let site = testing::site(
+            "permalinks",
+            &[
+                (
+                    "rey.toml",
+                    "[permalinks]\nblog = \"/:section/:year/:month/:day/:slug/\"\nnotes = \"n/:slug\"\n",
+                ),
+                (
+                    "content/blog/first.md",
+                    "+++\ntitle = \"Hello World\"\ndate = \"2024-03-05T10:00:00Z\"\n+++\n",
+                ),
+                (
+                    "content/blog/second.md",
+                    "+++\ntitle = \"Ignored\"\nslug = \"Custom Slug\"\ndate = \"2024-12-31\"\n+++\n",
+                ),
+                ("content/blog/undated.md", "+++\ntitle = \"Undated\"\n+++\n"),
+                ("content/notes/idea.md", "+++\ntitle = \"An idea\"\n+++\n"),
+                ("content/about.md", "+++\ntitle = \"About\"\n+++\n"),
+            ],
+        );
+        let mut paths: Vec<&str> = site.pages().map(|p| p.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "/about/",
+                "/blog/2024/03/05/hello-world/",
+                "/blog/2024/12/31/custom-slug/",
+                "/blog/undated/",
+                "/n/an-idea/",
+            ]
+        );
+        assert!(site.page("/n/an-idea/").is_some());
+    }
+
+    #[test]
+    fn permalinks_and_aliases_do_not_collide() {
+        let build = |name: &str, files: &[(&str, &str)]| {
+            let mut all = vec![
+                (
+                    "rey.toml",
+                    "[site]\nbase_url = \"https://example.com\"\n[permalinks]\nblog = \"/:year/:slug/\"\n",
+                ),
+                ("templates/page.html.tera", ""),
+            ];
+            all.extend_from_slice(files);
+            let site = testing::site(name, &all);
+            site.build(&testing::dir(&format!("{name}-out")))
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        let dated = |title: &str| format!("+++\ntitle = \"{title}\"\ndate = \"2024-01-01\"\n+++\n");
+
+        // Un alias sur l'URL d'une page
+        let hello = dated("Hello");
+        let err = build(
+            "permalink-alias",
+            &[
+                ("content/blog/hello.md", &hello),
+                ("content/old.md", "+++\naliases = [\"/2024/hello/\"]\n+++\n"),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "alias /2024/hello/ of /old/ would overwrite content/blog/hello.md"
+        );
+
+        // Deux pages au même permalien
+        let err = build(
+            "permalink-twice",
+            &[("content/blog/a.md", &hello), ("content/blog/b.md", &hello)],
+        )
+        .unwrap_err();
+        assert!(
+            err.ends_with("is written by both content/blog/a.md and content/blog/b.md")
+                || err.ends_with("is written by both content/blog/b.md and content/blog/a.md"),
+            "{err}"
+        );
+
+        // Une page déplacée par son permalien peut garder son ancienne URL en alias
+        let moved =
+            "+++\ntitle = \"Hello\"\ndate = \"2024-01-01\"\naliases = [\"/blog/hello/\"]\n+++\n";
+        build("permalink-moved", &[("content/blog/hello.md", moved)]).unwrap();
+    }
+}
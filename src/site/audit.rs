@@ -0,0 +1,296 @@
+//! Checks of the `Seo` of every content page, used by `rey seo audit`.
+//!
+//! Drafts, scheduled and expired pages are audited too, so that they are fixed
+//! before being published; their issues are warnings naming their status.
+
+use crate::{
+    Seo,
+    content::{Status, parse_date},
+};
+use std::{collections::HashMap, fmt};
+
+pub const TITLE_MAX: usize = 60;
+pub const DESCRIPTION_MAX: usize = 160;
+pub const OG_TYPES: &[&str] = &[
+    "website",
+    "article",
+    "book",
+    "profile",
+    "music.song",
+    "music.album",
+    "music.playlist",
+    "music.radio_station",
+    "video.movie",
+    "video.episode",
+    "video.tv_show",
+    "video.other",
+];
+pub const TWITTER_CARDS: &[&str] = &["summary", "summary_large_image", "app", "player"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A content page to audit.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: String,
+    /// `Seo` of the page, with the title, description and `updated` written in its
+    /// front matter rather than their fallbacks.
+    pub seo: Seo,
+    /// Publication date written in the front matter.
+    pub date: Option<String>,
+    /// Publication state of the page; sections are published.
+    pub status: Status,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub path: String,
+    pub level: Level,
+    pub message: String,
+}
+
+/// Audits the pages, returning the issues sorted by path then level.
+pub fn audit(entries: &[Entry]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut push = |path: &str, level, message: String| {
+        issues.push(Issue {
+            path: path.to_string(),
+            level,
+            message,
+        })
+    };
+
+    for Entry {
+        path, seo, date, ..
+    } in entries
+    {
+        let title = seo.title.trim().chars().count();
+        if title == 0 {
+            push(path, Level::Error, "missing title".to_string());
+        } else if title > TITLE_MAX {
+            push(
+                path,
+                Level::Warning,
+                format!("title too long ({title} > {TITLE_MAX} chars)"),
+            );
+        }
+
+        let description = seo.description.trim().chars().count();
+        if description == 0 {
+            push(path, Level::Error, "missing description".to_string());
+        } else if description > DESCRIPTION_MAX {
+            push(
+                path,
+                Level::Warning,
+                format!("description too long ({description} > {DESCRIPTION_MAX} chars)"),
+            );
+        }
+
+        if seo.og_image.as_deref().is_none_or(|i| i.trim().is_empty()) {
+            push(path, Level::Warning, "missing og_image".to_string());
+        }
+        if let Some(url) = seo.canonical_url.as_deref()
+            && !is_absolute_url(url)
+        {
+            push(path, Level::Error, format!("invalid canonical_url `{url}`"));
+        }
+        if let Some(t) = seo.og_type.as_deref()
+            && !OG_TYPES.contains(&t)
+        {
+            push(path, Level::Error, format!("unknown og_type `{t}`"));
+        }
+        if let Some(c) = seo.twitter_card.as_deref()
+            && !TWITTER_CARDS.contains(&c)
+        {
+            push(path, Level::Error, format!("unknown twitter_card `{c}`"));
+        }
+        for (field, value) in [("date", date), ("updated", &seo.updated)] {
+            if let Some(v) = value.as_deref()
+                && parse_date(v).is_none()
+            {
+                push(
+                    path,
+                    Level::Error,
+                    format!("`{field}` is not a date: `{v}`"),
+                );
+            }
+        }
+        if let Some(raw) = seo.json_ld.as_deref()
+            && let Err(e) = serde_json::from_str::<serde_json::Value>(raw)
+        {
+            push(
+                path,
+                Level::Error,
+                format!("`json_ld` is not valid JSON: {e}"),
+            );
+        }
+    }
+
+    for field in ["title", "description"] {
+        let mut seen: HashMap<String, Vec<&str>> = HashMap::new();
+        for Entry { path, seo, .. } in entries {
+            let v = match field {
+                "title" => &seo.title,
+                _ => &seo.description,
+            };
+            let v = v.trim().to_lowercase();
+            if !v.is_empty() {
+                seen.entry(v).or_default().push(path);
+            }
+        }
+        for paths in seen.values().filter(|p| p.len() > 1) {
+            for path in paths {
+                let others: Vec<&str> = paths.iter().copied().filter(|p| p != path).collect();
+                push(
+                    path,
+                    Level::Warning,
+                    format!("duplicate {field} (also on {})", others.join(", ")),
+                );
+            }
+        }
+    }
+
+    // Une page pas encore publiée ne fait pas échouer l'audit
+    let status: HashMap<&str, Status> = entries
+        .iter()
+        .map(|e| (e.path.as_str(), e.status))
+        .collect();
+    for issue in &mut issues {
+        if let Some(s) = status
+            .get(issue.path.as_str())
+            .filter(|s| **s != Status::Published)
+        {
+            issue.level = Level::Warning;
+            issue.message = format!("{} ({s} page)", issue.message);
+        }
+    }
+
+    issues.sort_by(|a, b| {
+        a.path
+            .cmp(&b.path)
+            .then(a.level.cmp(&b.level))
+            .then_with(|| a.message.cmp(&b.message))
+    });
+    issues
+}
+
+/// `http(s)://host/…` without whitespace.
+pub fn is_absolute_url(url: &str) -> bool {
+    let Some(rest) = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+    else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    !host.is_empty() && !url.chars().any(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, date: Option<&str>) -> Entry {
+        let mut seo = Seo::new();
+        seo.with_title(&format!("Title of {path}"))
+            .with_desc(&format!("Description of {path}"))
+            .with_og_image("/cover.png")
+            .with_canonical(&format!("https://ex.com{path}"));
+        Entry {
+            path: path.to_string(),
+            seo,
+            date: date.map(str::to_string),
+            status: Status::Published,
+        }
+    }
+
+    fn messages(entries: &[Entry]) -> Vec<String> {
+        audit(entries).into_iter().map(|i| i.message).collect()
+    }
+
+    #[test]
+    fn complete_pages_have_no_issue() {
+        assert!(messages(&[entry("/a/", Some("2025-01-31")), entry("/b/", None)]).is_empty());
+    }
+
+    #[test]
+    fn dates_are_reported_by_field() {
+        let mut page = entry("/a/", Some("2025-31-01"));
+        page.seo.with_updated("31/01/2025");
+        assert_eq!(
+            messages(&[page]),
+            [
+                "`date` is not a date: `2025-31-01`",
+                "`updated` is not a date: `31/01/2025`",
+            ]
+        );
+        // Toutes les dates qu'accepte une page passent l'audit
+        for date in [
+            "2025-01-31",
+            "2025-01-31 08:30:00",
+            "2025-01-31T08:30:00",
+            "2025-01-31T08:30:00+02:00",
+        ] {
+            let mut page = entry("/a/", Some(date));
+            page.seo.with_updated(date);
+            assert!(messages(&[page]).is_empty(), "{date}");
+        }
+    }
+
+    #[test]
+    fn unpublished_pages_get_warnings() {
+        let mut page = entry("/later/", None);
+        page.seo.with_og_type("bogus");
+        page.status = Status::Scheduled;
+        let issues = audit(&[page, entry("/now/", None)]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].level, Level::Warning);
+        assert_eq!(
+            issues[0].message,
+            "unknown og_type `bogus` (scheduled page)"
+        );
+    }
+
+    #[test]
+    fn missing_invalid_and_duplicate_fields() {
+        let mut a = entry("/a/", None);
+        a.seo
+            .with_title("")
+            .with_canonical("/a/")
+            .with_og_type("blog")
+            .with_json_ld("{");
+        let mut b = entry("/b/", None);
+        b.seo.with_desc("Description of /c/");
+        let messages = messages(&[a, b, entry("/c/", None)]);
+        for expected in [
+            "missing title",
+            "invalid canonical_url `/a/`",
+            "unknown og_type `blog`",
+            "duplicate description (also on /c/)",
+            "duplicate description (also on /b/)",
+        ] {
+            assert!(
+                messages.iter().any(|m| m == expected),
+                "{expected}: {messages:?}"
+            );
+        }
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.starts_with("`json_ld` is not valid JSON"))
+        );
+    }
+}
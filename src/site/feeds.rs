@@ -0,0 +1,489 @@
+//! RSS 2.0, Atom and JSON Feed 1.1 feeds of the dated pages, configured by `[feeds]`.
+//!
+//! Besides the site-wide feeds, every term of the taxonomies listed in
+//! `[feeds] taxonomies` gets its own, e.g. `/tags/rust/atom.xml`.
+
+use crate::{
+    content::{Page, parse_date},
+    render::helpers::escape,
+    site::{Output, Site, taxonomies},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::{fmt::Write as _, path::Path};
+
+pub const RSS_FILE: &str = "rss.xml";
+pub const ATOM_FILE: &str = "atom.xml";
+pub const JSON_FILE: &str = "feed.json";
+pub const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+/// A feed and the pages it syndicates, newest first.
+pub struct Feed<'a> {
+    pub title: String,
+    pub description: String,
+    /// Directory of the feed files, e.g. `/` or `/tags/rust/`.
+    pub dir: String,
+    /// HTML page the feed mirrors.
+    pub link: String,
+    pub entries: Vec<&'a Page>,
+}
+
+impl Feed<'_> {
+    pub fn path(&self, file: &str) -> String {
+        format!("{}{file}", self.dir)
+    }
+
+    /// Most recent update of the entries, or now for an empty feed.
+    pub fn updated(&self) -> DateTime<Utc> {
+        self.entries
+            .iter()
+            .filter_map(|p| updated(p))
+            .max()
+            .unwrap_or_else(Utc::now)
+    }
+}
+
+/// Dated pages eligible for the feeds, newest first, limited to `[feeds] limit`.
+/// Only rendered pages are listed, so drafts need both `--drafts` and
+/// `[feeds] drafts`.
+pub fn entries(site: &Site, keep: impl Fn(&Page) -> bool) -> Vec<&Page> {
+    select(site, site.pages().filter(|p| keep(p)))
+}
+
+/// The [`entries`] among `pages`, e.g. the pages of a taxonomy term.
+pub fn select<'a>(site: &Site, pages: impl IntoIterator<Item = &'a Page>) -> Vec<&'a Page> {
+    let feeds = &site.config.feeds;
+    let mut pages: Vec<&Page> = pages
+        .into_iter()
+        .filter(|p| p.published().is_some())
+        .filter(|p| feeds.drafts || !p.draft)
+        .filter(|p| feeds.sections.is_empty() || feeds.sections.contains(&p.section))
+        .collect();
+    pages.sort_by_key(|p| std::cmp::Reverse(p.published()));
+    pages.truncate(feeds.limit);
+    pages
+}
+
+/// A feed URL, exposed to templates as `feeds` for `<link rel="alternate">` tags.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedLink {
+    pub title: String,
+    /// MIME type: `application/rss+xml`, `application/atom+xml` or `application/feed+json`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub href: String,
+}
+
+/// Links to the feeds enabled in `[feeds]` located in `dir`.
+pub fn links(site: &Site, dir: &str, title: &str) -> Vec<FeedLink> {
+    let feeds = &site.config.feeds;
+    [
+        (feeds.rss, RSS_FILE, "application/rss+xml"),
+        (feeds.atom, ATOM_FILE, "application/atom+xml"),
+        (feeds.json, JSON_FILE, "application/feed+json"),
+    ]
+    .into_iter()
+    .filter(|(enabled, _, _)| *enabled)
+    .map(|(_, file, kind)| FeedLink {
+        title: title.to_string(),
+        kind,
+        href: site.config.url(&format!("{dir}{file}")),
+    })
+    .collect()
+}
+
+/// URL directory of a taxonomy term: `/tags/rust/`.
+pub fn term_dir(taxonomy: &str, term: &str) -> String {
+    format!("/{}/{}/", slug::slugify(taxonomy), slug::slugify(term))
+}
+
+/// Title of the feed of a taxonomy term.
+pub fn term_title(site: &Site, term: &str) -> String {
+    if site.config.site.title.is_empty() {
+        term.to_string()
+    } else {
+        format!("{} – {term}", site.config.site.title)
+    }
+}
+
+/// Writes the site-wide feeds and the feeds of every taxonomy term, returning the
+/// number of written files.
+pub fn write_all(site: &Site, output: &mut Output) -> anyhow::Result<usize> {
+    let feed = Feed {
+        title: site.config.site.title.clone(),
+        description: site.config.site.description.clone(),
+        dir: "/".to_string(),
+        link: site.config.url("/"),
+        entries: entries(site, |_| true),
+    };
+    let mut written = write_feed(site, &feed, output)?;
+
+    // Les termes syndiqués ont déjà leurs liens, posés par `taxonomies::collect`
+    for taxonomy in &site.taxonomies {
+        for term in taxonomy.terms.iter().filter(|t| !t.feeds.is_empty()) {
+            let feed = Feed {
+                title: term_title(site, &term.name),
+                description: site.config.site.description.clone(),
+                dir: term.path.clone(),
+                link: site.config.url(&term.path),
+                entries: select(site, taxonomies::pages(site, term)),
+            };
+            written += write_feed(site, &feed, output)?;
+        }
+    }
+    Ok(written)
+}
+
+/// Writes the RSS, Atom and JSON files of `feed` enabled in `[feeds]`.
+pub fn write_feed(site: &Site, feed: &Feed, output: &mut Output) -> anyhow::Result<usize> {
+    let mut written = 0;
+    let dir = feed.dir.trim_start_matches('/');
+    if site.config.feeds.rss {
+        output.write(
+            Path::new(dir).join(RSS_FILE),
+            &format!("feed {}", feed.dir),
+            &rss(site, feed),
+        )?;
+        written += 1;
+    }
+    if site.config.feeds.atom {
+        output.write(
+            Path::new(dir).join(ATOM_FILE),
+            &format!("feed {}", feed.dir),
+            &atom(site, feed),
+        )?;
+        written += 1;
+    }
+    if site.config.feeds.json {
+        output.write(
+            Path::new(dir).join(JSON_FILE),
+            &format!("feed {}", feed.dir),
+            &json_feed(site, feed),
+        )?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Last update of a page: `Seo::updated`, then its date.
+pub fn updated(page: &Page) -> Option<DateTime<Utc>> {
+    page.seo
+        .updated
+        .as_deref()
+        .and_then(parse_date)
+        .or_else(|| page.published())
+}
+
+/// HTML of a feed entry: the whole page with `[feeds] full_content`, otherwise its
+/// summary (see [`Page::summary`]).
+pub fn entry_html(site: &Site, page: &Page) -> String {
+    if site.config.feeds.full_content {
+        page.content.clone()
+    } else {
+        page.summary.clone()
+    }
+}
+
+pub fn rss(site: &Site, feed: &Feed) -> String {
+    let mut xml = String::new();
+    let self_url = site.config.url(&feed.path(RSS_FILE));
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/">"#);
+    xml.push_str("\n<channel>\n");
+    let _ = writeln!(xml, "<title>{}</title>", escape(&feed.title));
+    let _ = writeln!(xml, "<link>{}</link>", escape(&feed.link));
+    let _ = writeln!(
+        xml,
+        "<description>{}</description>",
+        escape(&feed.description)
+    );
+    if let Some(lang) = &site.config.seo.lang {
+        let _ = writeln!(xml, "<language>{}</language>", escape(lang));
+    }
+    let _ = writeln!(
+        xml,
+        "<lastBuildDate>{}</lastBuildDate>",
+        feed.updated().to_rfc2822()
+    );
+    let _ = writeln!(
+        xml,
+        r#"<atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+        escape(&self_url)
+    );
+    for page in &feed.entries {
+        let url = site.config.url(&page.path);
+        xml.push_str("<item>\n");
+        let _ = writeln!(xml, "<title>{}</title>", escape(&page.title));
+        let _ = writeln!(xml, "<link>{}</link>", escape(&url));
+        let _ = writeln!(xml, r#"<guid isPermaLink="true">{}</guid>"#, escape(&url));
+        if let Some(date) = page.published() {
+            let _ = writeln!(xml, "<pubDate>{}</pubDate>", date.to_rfc2822());
+        }
+        if let Some(author) = &page.seo.author {
+            let _ = writeln!(xml, "<dc:creator>{}</dc:creator>", escape(author));
+        }
+        for tag in &page.tags {
+            let _ = writeln!(xml, "<category>{}</category>", escape(tag));
+        }
+        let _ = writeln!(
+            xml,
+            "<description>{}</description>",
+            escape(&entry_html(site, page))
+        );
+        xml.push_str("</item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+pub fn atom(site: &Site, feed: &Feed) -> String {
+    let mut xml = String::new();
+    let self_url = site.config.url(&feed.path(ATOM_FILE));
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    match &site.config.seo.lang {
+        Some(lang) => {
+            let _ = writeln!(
+                xml,
+                r#"<feed xmlns="http://www.w3.org/2005/Atom" xml:lang="{}">"#,
+                escape(lang)
+            );
+        }
+        None => xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n"),
+    }
+    let _ = writeln!(xml, "<title>{}</title>", escape(&feed.title));
+    if !feed.description.is_empty() {
+        let _ = writeln!(xml, "<subtitle>{}</subtitle>", escape(&feed.description));
+    }
+    let _ = writeln!(
+        xml,
+        r#"<link href="{}" rel="alternate" type="text/html"/>"#,
+        escape(&feed.link)
+    );
+    let _ = writeln!(
+        xml,
+        r#"<link href="{}" rel="self" type="application/atom+xml"/>"#,
+        escape(&self_url)
+    );
+    let _ = writeln!(xml, "<id>{}</id>", escape(&self_url));
+    let _ = writeln!(xml, "<updated>{}</updated>", feed.updated().to_rfc3339());
+    // Atom exige un auteur : celui du site, à défaut son titre
+    let site_author = site
+        .config
+        .seo
+        .author
+        .clone()
+        .unwrap_or_else(|| site.config.site.title.clone());
+    let _ = writeln!(
+        xml,
+        "<author><name>{}</name></author>",
+        escape(&site_author)
+    );
+    for page in &feed.entries {
+        let url = site.config.url(&page.path);
+        xml.push_str("<entry>\n");
+        let _ = writeln!(xml, "<title>{}</title>", escape(&page.title));
+        let _ = writeln!(
+            xml,
+            r#"<link href="{}" rel="alternate" type="text/html"/>"#,
+            escape(&url)
+        );
+        let _ = writeln!(xml, "<id>{}</id>", escape(&url));
+        if let Some(date) = page.published() {
+            let _ = writeln!(xml, "<published>{}</published>", date.to_rfc3339());
+        }
+        let updated = updated(page).unwrap_or_else(|| feed.updated());
+        let _ = writeln!(xml, "<updated>{}</updated>", updated.to_rfc3339());
+        if let Some(author) = &page.seo.author {
+            let _ = writeln!(xml, "<author><name>{}</name></author>", escape(author));
+        }
+        for tag in &page.tags {
+            let _ = writeln!(xml, r#"<category term="{}"/>"#, escape(tag));
+        }
+        let element = if site.config.feeds.full_content {
+            "content"
+        } else {
+            "summary"
+        };
+        let _ = writeln!(
+            xml,
+            r#"<{element} type="html">{}</{element}>"#,
+            escape(&entry_html(site, page))
+        );
+        xml.push_str("</entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+pub fn json_feed(site: &Site, feed: &Feed) -> String {
+    let items: Vec<serde_json::Value> = feed
+        .entries
+        .iter()
+        .map(|page| {
+            let url = site.config.url(&page.path);
+            let html = entry_html(site, page);
+            let mut item = json!({
+                "id": url,
+                "url": url,
+                "title": page.title,
+                "content_html": html,
+                "tags": page.tags,
+            });
+            if !page.description.is_empty() {
+                item["summary"] = json!(page.description);
+            }
+            if let Some(date) = page.published() {
+                item["date_published"] = json!(date.to_rfc3339());
+            }
+            if let Some(date) = updated(page) {
+                item["date_modified"] = json!(date.to_rfc3339());
+            }
+            if let Some(author) = &page.seo.author {
+                item["authors"] = json!([{ "name": author }]);
+            }
+            if let Some(image) = &page.seo.og_image {
+                item["image"] = json!(if image.starts_with('/') {
+                    site.config.url(image)
+                } else {
+                    image.clone()
+                });
+            }
+            item
+        })
+        .collect();
+
+    let mut json = json!({
+        "version": JSON_FEED_VERSION,
+        "title": feed.title,
+        "home_page_url": feed.link,
+        "feed_url": site.config.url(&feed.path(JSON_FILE)),
+        "items": items,
+    });
+    if !feed.description.is_empty() {
+        json["description"] = json!(feed.description);
+    }
+    if let Some(lang) = &site.config.seo.lang {
+        json["language"] = json!(lang);
+    }
+    if let Some(author) = &site.config.seo.author {
+        json["authors"] = json!([{ "name": author }]);
+    }
+    serde_json::to_string_pretty(&json).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const CONFIG: &str = "[site]\ntitle = \"Rey & co\"\nbase_url = \"https://example.com/\"\n";
+
+    fn site(name: &str, config: &str) -> Site {
+        testing::site(
+            name,
+            &[
+                ("rey.toml", &format!("{CONFIG}{config}")),
+                (
+                    "content/blog/a.md",
+                    "+++\ntitle = \"<One> & \\\"two\\\"\"\ndate = \"2024-01-01\"\ntags = [\"Rust\"]\n+++\nA **bold** start.\n",
+                ),
+                (
+                    "content/blog/b.md",
+                    "+++\ntitle = \"B\"\ndate = \"2024-02-01\"\n+++\n",
+                ),
+                (
+                    "content/blog/c.md",
+                    "+++\ntitle = \"C\"\ndate = \"2024-03-01\"\ndraft = true\n+++\n",
+                ),
+                (
+                    "content/notes/n.md",
+                    "+++\ntitle = \"N\"\ndate = \"2024-04-01\"\ntags = [\"rust\"]\n+++\n",
+                ),
+                ("content/about.md", "+++\ntitle = \"About\"\n+++\n"),
+            ],
+        )
+    }
+
+    fn paths(pages: Vec<&Page>) -> Vec<&str> {
+        pages.into_iter().map(|p| p.path.as_str()).collect()
+    }
+
+    #[test]
+    fn entries_are_the_newest_dated_pages() {
+        let site = site("feeds-entries", "[feeds]\nlimit = 2\n");
+        assert_eq!(paths(entries(&site, |_| true)), ["/notes/n/", "/blog/b/"]);
+
+        let site = self::site("feeds-all", "");
+        assert_eq!(
+            paths(entries(&site, |_| true)),
+            ["/notes/n/", "/blog/b/", "/blog/a/"]
+        );
+        let site = self::site("feeds-sections", "[feeds]\nsections = [\"blog\"]\n");
+        assert_eq!(paths(entries(&site, |_| true)), ["/blog/b/", "/blog/a/"]);
+    }
+
+    #[test]
+    fn drafts_need_to_be_rendered_and_enabled() {
+        let site = self::site("feeds-drafts-off", "[build]\ndrafts = true\n");
+        assert!(!paths(entries(&site, |_| true)).contains(&"/blog/c/"));
+        let site = self::site("feeds-drafts-unrendered", "[feeds]\ndrafts = true\n");
+        assert!(!paths(entries(&site, |_| true)).contains(&"/blog/c/"));
+        let site = self::site(
+            "feeds-drafts-on",
+            "[build]\ndrafts = true\n[feeds]\ndrafts = true\n",
+        );
+        assert_eq!(paths(entries(&site, |_| true))[1], "/blog/c/");
+    }
+
+    #[test]
+    fn feeds_have_absolute_links_and_escaped_text() {
+        let site = self::site("feeds-xml", "[feeds]\nsections = [\"blog\"]\n");
+        let feed = Feed {
+            title: site.config.site.title.clone(),
+            description: String::new(),
+            dir: "/".to_string(),
+            link: site.config.url("/"),
+            entries: entries(&site, |_| true),
+        };
+
+        let rss = rss(&site, &feed);
+        assert!(rss.contains("<title>Rey &amp; co</title>"), "{rss}");
+        assert!(rss.contains("<title>&lt;One&gt; &amp; &quot;two&quot;</title>"));
+        assert!(rss.contains("<link>https://example.com/blog/a/</link>"));
+        assert!(rss.contains(r#"<atom:link href="https://example.com/rss.xml" rel="self""#));
+        assert!(rss.contains("&lt;strong&gt;bold&lt;/strong&gt;"));
+        assert!(rss.contains("<category>Rust</category>"));
+        assert!(!rss.contains("<strong>"));
+
+        let atom = atom(&site, &feed);
+        assert!(
+            atom.contains("<id>https://example.com/atom.xml</id>"),
+            "{atom}"
+        );
+        assert!(atom.contains(r#"<link href="https://example.com/blog/b/" rel="alternate""#));
+        assert!(atom.contains("<author><name>Rey &amp; co</name></author>"));
+        assert!(atom.contains(r#"<summary type="html">"#));
+
+        let json: serde_json::Value = serde_json::from_str(&json_feed(&site, &feed)).unwrap();
+        assert_eq!(json["feed_url"], "https://example.com/feed.json");
+        assert_eq!(json["items"][1]["url"], "https://example.com/blog/a/");
+        assert_eq!(json["items"][1]["title"], "<One> & \"two\"");
+    }
+
+    #[test]
+    fn terms_get_their_own_feeds() {
+        let site = self::site("feeds-terms", "[feeds]\nlimit = 1\n");
+        let out = testing::dir("feeds-terms-out");
+        let mut output = Output::new(&out);
+        // Flux du site et de `rust`, `categories` n'ayant aucun terme
+        assert_eq!(write_all(&site, &mut output).unwrap(), 6);
+        let atom = std::fs::read_to_string(out.join("tags/rust/atom.xml")).unwrap();
+        assert!(atom.contains("<id>https://example.com/tags/rust/atom.xml</id>"));
+        assert!(atom.contains("https://example.com/notes/n/"));
+        assert!(!atom.contains("https://example.com/blog/a/"), "{atom}");
+        assert!(out.join("tags/rust/feed.json").exists());
+    }
+}
@@ -0,0 +1,843 @@
+//! Static rendering of a rey project into a self-contained directory.
+//!
+//! The templates of `templates/` are loaded into the same Tera setup as
+//! [`crate::render::attach`], every page template and every markdown file of
+//! `content/` is rendered to HTML and the `public/` assets are copied next to it,
+//! so the output can be deployed on any static hosting.
+
+use crate::{
+    Seo,
+    config::{CONFIG_FILE, Config},
+    content::{self, CONTENT_DIR, Page, Publish, Section, Status},
+    render::{self, shortcodes},
+};
+use anyhow::{Context as _, bail};
+use chrono::{DateTime, Utc};
+use rocket_dyn_templates::tera::{Context, Tera};
+use series::Series;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Component, Path, PathBuf},
+};
+use taxonomies::Taxonomy;
+
+pub mod aliases;
+pub mod audit;
+pub mod feeds;
+pub mod paginate;
+pub mod related;
+pub mod series;
+pub mod sitemap;
+pub mod taxonomies;
+
+pub const TEMPLATES_DIR: &str = "templates";
+pub const PUBLIC_DIR: &str = "public";
+
+pub const PAGE_TEMPLATE: &str = "page";
+pub const SECTION_TEMPLATE: &str = "section";
+
+pub struct Site {
+    pub config: Config,
+    pub tera: Tera,
+    pub sections: Vec<Section>,
+    /// Pages left out of `sections`: expired ones, drafts and scheduled ones unless
+    /// `--drafts` / `--future` (or `[build] drafts` / `future`) are set.
+    pub unpublished: Vec<Page>,
+    /// Time the site was loaded at, deciding which pages are scheduled or expired.
+    pub now: DateTime<Utc>,
+    /// Terms of the `[[taxonomies]]`, collected from the published pages.
+    pub taxonomies: Vec<Taxonomy>,
+    /// Series of the published pages.
+    pub series: Vec<Series>,
+    /// URL paths of the related pages of each published page, by URL path.
+    pub related: HashMap<String, Vec<String>>,
+    /// Position of every published page in `sections`, by URL path.
+    index: HashMap<String, (usize, usize)>,
+}
+
+/// Summary of a finished build.
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    pub pages: usize,
+    /// Other generated files: feeds, sitemaps…
+    pub files: usize,
+    pub assets: usize,
+}
+
+impl Site {
+    /// Loads `rey.toml` and the project templates from the current directory,
+    /// publishing drafts and scheduled pages as `REY_DRAFTS` / `REY_FUTURE` say.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_with(Publish::from_env())
+    }
+
+    /// Same as [`Site::load`], publishing drafts and scheduled pages as `publish` says
+    /// (or as `[build] drafts` / `future` do).
+    pub fn load_with(publish: Publish) -> anyhow::Result<Self> {
+        Self::open(Path::new(""), publish)
+    }
+
+    /// Same as [`Site::load_with`], for the project at `root`.
+    pub fn open(root: &Path, mut publish: Publish) -> anyhow::Result<Self> {
+        let config = Config::from_file(&root.join(CONFIG_FILE))?;
+        publish.drafts |= config.build.drafts;
+        publish.future |= config.build.future;
+        let tera = render::engine(&root.join(TEMPLATES_DIR), &config)
+            .context("failed to load templates")?;
+        let mut sections = content::load(&root.join(CONTENT_DIR), &tera, &config)?;
+        for section in &mut sections {
+            complete_seo(&mut section.seo, &config, &section.path);
+            let permalink = config.permalinks.get(&section.name);
+            for page in &mut section.pages {
+                if let Some(pattern) = permalink {
+                    page.apply_permalink(pattern);
+                }
+                if page.seo.keywords.is_empty() {
+                    page.seo.keywords.clone_from(&page.tags);
+                }
+                if page.seo.updated.is_none() {
+                    page.seo.updated.clone_from(&page.date);
+                }
+                if page.seo.og_type.is_none() && page.date.is_some() {
+                    page.seo.with_og_type("article");
+                }
+                page.seo.with_slug(&page.slug);
+                complete_seo(&mut page.seo, &config, &page.path);
+            }
+        }
+        let now = Utc::now();
+        let mut unpublished = Vec::new();
+        for section in &mut sections {
+            let (kept, left): (Vec<Page>, Vec<Page>) = section
+                .pages
+                .drain(..)
+                .partition(|p| publish.allows(p.status(now)));
+            section.pages = kept;
+            unpublished.extend(left);
+        }
+        let mut site = Self {
+            config,
+            tera,
+            sections,
+            unpublished,
+            now,
+            taxonomies: Vec::new(),
+            series: Vec::new(),
+            related: HashMap::new(),
+            index: HashMap::new(),
+        };
+        for (i, section) in site.sections.iter().enumerate() {
+            for (j, page) in section.pages.iter().enumerate() {
+                site.index.insert(page.path.clone(), (i, j));
+            }
+        }
+        site.taxonomies = taxonomies::collect(&site);
+        site.series = series::collect(&site);
+        site.related = related::compute(&site);
+        Ok(site)
+    }
+
+    /// Audit entries of every content page (sections with an `_index.md` and pages,
+    /// unpublished ones included). Title, description and dates are the ones written
+    /// in the front matter, not their fallbacks, so that missing or invalid ones can
+    /// be reported.
+    pub fn seo_entries(&self) -> Vec<audit::Entry> {
+        let authored = |path: &str, seo: &Seo, title: &str, description: &str| {
+            let mut seo = seo.clone();
+            seo.with_title(title).with_desc(description);
+            audit::Entry {
+                path: path.to_string(),
+                seo,
+                date: None,
+                status: Status::Published,
+            }
+        };
+        let sections = self
+            .sections
+            .iter()
+            .filter(|s| s.has_index)
+            .map(|s| authored(&s.path, &s.seo, &s.title, &s.description));
+        let pages = self.pages().chain(&self.unpublished).map(|p| {
+            let mut entry = authored(&p.path, &p.seo, &p.title, &p.description);
+            entry.status = p.status(self.now);
+            // `updated` vaut la date de la page quand il manque
+            entry.seo.updated.clone_from(&p.updated);
+            entry.date.clone_from(&p.date);
+            entry
+        });
+        sections.chain(pages).collect()
+    }
+
+    /// Every published page, all sections included.
+    pub fn pages(&self) -> impl Iterator<Item = &Page> {
+        self.sections.iter().flat_map(|s| s.pages.iter())
+    }
+
+    /// Every page that is not plainly published (drafts, scheduled and expired ones,
+    /// rendered or not) with its status, soonest first.
+    pub fn pending(&self) -> Vec<(&Page, Status)> {
+        let mut pending: Vec<(&Page, Status)> = self
+            .pages()
+            .chain(&self.unpublished)
+            .map(|p| (p, p.status(self.now)))
+            .filter(|(_, status)| *status != Status::Published)
+            .collect();
+        pending.sort_by(|(a, sa), (b, sb)| {
+            sa.cmp(sb)
+                .then_with(|| a.published().cmp(&b.published()))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        pending
+    }
+
+    /// Names of the templates rendered as pages: everything except the kit templates,
+    /// the templates used by `content/` (`index` too when `content/_index.md` exists),
+    /// files or directories starting with `_` and the directories listed in
+    /// `[build] ignore`.
+    pub fn page_templates(&self) -> Vec<String> {
+        let mut content = vec![PAGE_TEMPLATE, SECTION_TEMPLATE, series::SERIES_TEMPLATE];
+        for section in &self.sections {
+            content.extend(section.template.as_deref());
+            // Le _index.md de la racine est rendu avec le template index
+            if section.name.is_empty() && section.has_index && section.template.is_none() {
+                content.push("index");
+            }
+            content.extend(section.pages.iter().filter_map(|p| p.template.as_deref()));
+        }
+        for taxonomy in &self.taxonomies {
+            content.push(&taxonomy.template);
+            content.push(&taxonomy.term_template);
+        }
+        let mut names: Vec<String> = self
+            .tera
+            .get_template_names()
+            .filter(|name| !name.ends_with(".tera") && !content.contains(name))
+            .filter(|name| !name.starts_with(&format!("{}/", shortcodes::SHORTCODES_DIR)))
+            .filter(|name| {
+                let parts: Vec<&str> = name.split('/').collect();
+                let (_, dirs) = parts.split_last().unwrap_or((&"", &[]));
+                parts.iter().all(|p| !p.starts_with('_'))
+                    && dirs
+                        .iter()
+                        .all(|d| !self.config.build.ignore.iter().any(|i| i == d))
+            })
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Base context shared by every rendered page.
+    pub fn context(&self, path: &str) -> Context {
+        let mut ctx = Context::new();
+        ctx.insert("site", &self.config.site);
+        ctx.insert("title", &self.config.site.title);
+        ctx.insert("path", path);
+        ctx.insert("current_url", &self.config.url(path));
+        ctx.insert("seo", &self.seo(path));
+        ctx.insert("feeds", &feeds::links(self, "/", &self.config.site.title));
+        let taxonomies: BTreeMap<&str, &Taxonomy> = self
+            .taxonomies
+            .iter()
+            .map(|t| (t.name.as_str(), t))
+            .collect();
+        ctx.insert("taxonomies", &taxonomies);
+        ctx
+    }
+
+    /// Related pages of `page`, most related first.
+    pub fn related(&self, page: &Page) -> Vec<&Page> {
+        let Some(paths) = self.related.get(&page.path) else {
+            return Vec::new();
+        };
+        paths.iter().filter_map(|path| self.page(path)).collect()
+    }
+
+    /// Published page at the URL path `path`.
+    pub fn page(&self, path: &str) -> Option<&Page> {
+        let &(section, page) = self.index.get(path)?;
+        self.sections.get(section)?.pages.get(page)
+    }
+
+    /// Series `page` is a part of.
+    pub fn series_of(&self, page: &Page) -> Option<&Series> {
+        let slug = slug::slugify(page.series.as_deref()?);
+        self.series.iter().find(|s| s.slug == slug)
+    }
+
+    /// Template `section` is rendered with: the one of its `_index.md`, else `index`
+    /// for the root of `content/` and `section` for the others. The root gets no page
+    /// without an `_index.md`, nor does a section without a template to use, as for
+    /// taxonomies and series.
+    pub fn section_template<'a>(&self, section: &'a Section) -> Option<&'a str> {
+        if section.name.is_empty() && !section.has_index {
+            return None;
+        }
+        if let Some(template) = section.template.as_deref() {
+            return Some(template);
+        }
+        let default = if section.name.is_empty() {
+            "index"
+        } else {
+            SECTION_TEMPLATE
+        };
+        self.has_template(default).then_some(default)
+    }
+
+    /// Whether the project (or the kit) provides the template `name`.
+    pub fn has_template(&self, name: &str) -> bool {
+        self.tera.get_template_names().any(|n| n == name)
+    }
+
+    /// `Seo` of a page without front matter: the site title and description over
+    /// the `[seo]` defaults.
+    pub fn seo(&self, path: &str) -> Seo {
+        let mut seo = Seo::new();
+        seo.with_title(&self.config.site.title)
+            .with_desc(&self.config.site.description);
+        complete_seo(&mut seo, &self.config, path);
+        seo
+    }
+
+    fn render(&self, template: &str, ctx: &Context) -> anyhow::Result<String> {
+        self.tera
+            .render(template, ctx)
+            .with_context(|| format!("failed to render {template}"))
+    }
+
+    /// Renders the whole site into `out`, which is emptied first. Two outputs
+    /// written to the same file (a page and a template, two permalinks…) fail the
+    /// build.
+    pub fn build(&self, out: &Path) -> anyhow::Result<BuildReport> {
+        let mut report = BuildReport::default();
+        check_output_dir(&std::env::current_dir()?, out)?;
+        check_base_url(&self.config.site.base_url)?;
+        if out.exists() {
+            fs::remove_dir_all(out)
+                .with_context(|| format!("failed to clean {}", out.display()))?;
+        }
+        fs::create_dir_all(out)?;
+        let mut output = Output::new(out);
+
+        let sections: BTreeMap<&str, &Section> =
+            self.sections.iter().map(|s| (s.name.as_str(), s)).collect();
+        for name in self.page_templates() {
+            let (path, file) = output_path(&name);
+            let mut ctx = self.context(&path);
+            ctx.insert("sections", &sections);
+            output.write(
+                file,
+                &format!("template {name}"),
+                &self.render(&name, &ctx)?,
+            )?;
+            report.pages += 1;
+        }
+
+        for section in &self.sections {
+            let Some(template) = self.section_template(section) else {
+                continue;
+            };
+            let pages: Vec<&Page> = section.pages.iter().collect();
+            let pattern = section
+                .paginate_path
+                .as_deref()
+                .unwrap_or(&self.config.pagination.path);
+            let per_page = section
+                .paginate_by
+                .unwrap_or(self.config.pagination.per_page);
+            let source = Path::new(CONTENT_DIR)
+                .join(&section.name)
+                .join(content::SECTION_INDEX);
+            for (path, paginator) in paginate::paginate(
+                &pages,
+                &section.path,
+                per_page,
+                pattern,
+                self.config.pagination.window,
+            ) {
+                let mut ctx = self.context(&path);
+                let mut seo = section.seo.clone();
+                if paginator.current > 1 {
+                    seo.with_canonical(&self.config.url(&path));
+                }
+                ctx.insert("section", section);
+                ctx.insert("paginator", &paginator);
+                ctx.insert("seo", &seo);
+                output.write(
+                    page_file(&path),
+                    &source.display().to_string(),
+                    &self.render(template, &ctx)?,
+                )?;
+                report.pages += 1;
+            }
+        }
+
+        for page in self.pages() {
+            let template = page.template.as_deref().unwrap_or(PAGE_TEMPLATE);
+            let mut ctx = self.context(&page.path);
+            ctx.insert("page", page);
+            ctx.insert("seo", &page.seo);
+            if let Some(series) = self.series_of(page) {
+                ctx.insert("series", &series.context(self, Some(&page.path)));
+            }
+            ctx.insert("related", &self.related(page));
+            output.write(
+                page_file(&page.path),
+                &format!("{CONTENT_DIR}/{}", page.source),
+                &self.render(template, &ctx)?,
+            )?;
+            report.pages += 1;
+        }
+
+        report.pages += self.build_taxonomies(&mut output)?;
+        if self.has_template(series::SERIES_TEMPLATE) {
+            for series in &self.series {
+                let mut ctx = self.context(&series.path);
+                let mut seo = self.seo(&series.path);
+                seo.with_title(&series.title);
+                ctx.insert("series", &series.context(self, None));
+                ctx.insert("seo", &seo);
+                output.write(
+                    page_file(&series.path),
+                    &format!("series {}", series.title),
+                    &self.render(series::SERIES_TEMPLATE, &ctx)?,
+                )?;
+                report.pages += 1;
+            }
+        }
+        report.files += feeds::write_all(self, &mut output)?;
+        report.files += sitemap::write_all(self, &mut output)?;
+        // Les alias passent en dernier, pour ne masquer aucun fichier généré
+        report.files += aliases::write_all(self, &mut output)?;
+
+        report.assets += copy_dir(Path::new(PUBLIC_DIR), &out.join(PUBLIC_DIR))?;
+        render::extract_assets(&out.join("kit"))?;
+        Ok(report)
+    }
+
+    /// Renders the term index and the term list pages of every taxonomy whose
+    /// templates exist, returning the number of written pages.
+    fn build_taxonomies(&self, output: &mut Output) -> anyhow::Result<usize> {
+        let mut written = 0;
+        for taxonomy in &self.taxonomies {
+            if self.has_template(&taxonomy.template) {
+                let mut ctx = self.context(&taxonomy.path);
+                let mut seo = self.seo(&taxonomy.path);
+                seo.with_title(&taxonomy.title);
+                ctx.insert("taxonomy", taxonomy);
+                ctx.insert("seo", &seo);
+                output.write(
+                    page_file(&taxonomy.path),
+                    &format!("taxonomy {}", taxonomy.name),
+                    &self.render(&taxonomy.template, &ctx)?,
+                )?;
+                written += 1;
+            }
+            if !self.has_template(&taxonomy.term_template) {
+                continue;
+            }
+            let pattern = taxonomy
+                .paginate_path
+                .as_deref()
+                .unwrap_or(&self.config.pagination.path);
+            for term in &taxonomy.terms {
+                let pages = taxonomies::pages(self, term);
+                for (path, paginator) in paginate::paginate(
+                    &pages,
+                    &term.path,
+                    taxonomy.paginate,
+                    pattern,
+                    self.config.pagination.window,
+                ) {
+                    let mut ctx = self.context(&path);
+                    let mut seo = self.seo(&path);
+                    seo.with_title(&term.name);
+                    ctx.insert("taxonomy", taxonomy);
+                    ctx.insert("term", term);
+                    ctx.insert("paginator", &paginator);
+                    ctx.insert("seo", &seo);
+                    if !term.feeds.is_empty() {
+                        ctx.insert("feeds", &term.feeds);
+                    }
+                    output.write(
+                        page_file(&path),
+                        &format!("{} term {}", taxonomy.name, term.name),
+                        &self.render(&taxonomy.term_template, &ctx)?,
+                    )?;
+                    written += 1;
+                }
+            }
+        }
+        Ok(written)
+    }
+}
+
+/// Project directories the output directory may not be, nor be inside of.
+const SOURCE_DIRS: &[&str] = &[CONTENT_DIR, TEMPLATES_DIR, PUBLIC_DIR, "src", "front"];
+
+/// Refuses an output directory whose cleaning would delete the project at `root`:
+/// the project itself, one of its ancestors, or one of its source directories.
+pub fn check_output_dir(root: &Path, out: &Path) -> anyhow::Result<()> {
+    let root = absolute(Path::new("/"), root);
+    let out = absolute(&root, out);
+    if root.starts_with(&out) {
+        bail!(
+            "refusing to build into {}: it contains the project",
+            out.display()
+        );
+    }
+    if let Some(dir) = SOURCE_DIRS.iter().find(|d| out.starts_with(root.join(d))) {
+        bail!(
+            "refusing to build into {}: it is inside {dir}/",
+            out.display()
+        );
+    }
+    Ok(())
+}
+
+/// Refuses a `[site] base_url` a deployed site cannot use: unset (it then defaults
+/// to `http://localhost:8000`), not an absolute `http(s)` URL, or a loopback host.
+pub fn check_base_url(base_url: &str) -> anyhow::Result<()> {
+    let host = base_url
+        .strip_prefix("https://")
+        .or_else(|| base_url.strip_prefix("http://"))
+        .map(|rest| rest.split(['/', '?', '#']).next().unwrap_or_default())
+        .map(|authority| authority.rsplit('@').next().unwrap_or_default())
+        .unwrap_or_default();
+    // Le port suit le dernier `:`, sauf dans une adresse IPv6 entre crochets
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    if host.is_empty() {
+        bail!(
+            "`[site] base_url` of rey.toml is `{base_url}`: set it to the absolute URL the site is published at"
+        );
+    }
+    let host = host.to_ascii_lowercase();
+    if host == "localhost"
+        || host.ends_with(".localhost")
+        || host.starts_with("127.")
+        || host == "0.0.0.0"
+        || host == "[::1]"
+    {
+        bail!(
+            "`[site] base_url` of rey.toml is `{base_url}` (the default when it is unset): the canonical URLs, feeds and sitemap would point at this machine. Set it to the URL the site is published at"
+        );
+    }
+    Ok(())
+}
+
+/// `path` relative to `base`, its symbolic links resolved when it exists and its
+/// `.` and `..` folded otherwise.
+fn absolute(base: &Path, path: &Path) -> PathBuf {
+    let path = base.join(path);
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let mut absolute = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            c => absolute.push(c),
+        }
+    }
+    absolute
+}
+
+/// Fills the canonical URL from the page path, the slug from its last segment, the
+/// title and description from `[site]`, then the site-wide fields still empty from
+/// `[seo]`.
+fn complete_seo(seo: &mut Seo, config: &Config, path: &str) {
+    if seo.canonical_url.is_none() {
+        seo.with_canonical(&config.url(path));
+    }
+    if seo.slug.is_none()
+        && let Some(slug) = path.trim_matches('/').rsplit('/').next()
+        && !slug.is_empty()
+    {
+        seo.with_slug(slug);
+    }
+    if seo.title.is_empty() {
+        seo.with_title(&config.site.title);
+    }
+    if seo.description.is_empty() {
+        seo.with_desc(&config.site.description);
+    }
+    seo.with_defaults(&config.seo);
+}
+
+/// URL path and output file of a page template:
+/// `index` → `/` (`index.html`), `404` → `/404.html`, `web/about` → `/web/about/`
+/// (`web/about/index.html`).
+pub fn output_path(name: &str) -> (String, PathBuf) {
+    if name == "index" {
+        return ("/".to_string(), PathBuf::from("index.html"));
+    }
+    if name == "404" {
+        return ("/404.html".to_string(), PathBuf::from("404.html"));
+    }
+    let dir = name.strip_suffix("/index").unwrap_or(name);
+    (format!("/{dir}/"), Path::new(dir).join("index.html"))
+}
+
+/// Output file of a URL path: `/blog/hello/` → `blog/hello/index.html`.
+pub fn page_file(path: &str) -> PathBuf {
+    let path = path.trim_matches('/');
+    if path.ends_with(".html") || path.ends_with(".xml") || path.ends_with(".txt") {
+        PathBuf::from(path)
+    } else {
+        Path::new(path).join("index.html")
+    }
+}
+
+/// Output directory of a build, where every file is written once.
+pub struct Output {
+    dir: PathBuf,
+    /// What each written file holds, by path relative to `dir`.
+    written: BTreeMap<PathBuf, String>,
+}
+
+impl Output {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            written: BTreeMap::new(),
+        }
+    }
+
+    /// Writes `content` to `file`, relative to the output directory, `what` naming
+    /// its source; a file already written by another source is an error.
+    pub fn write(
+        &mut self,
+        file: impl AsRef<Path>,
+        what: &str,
+        content: &str,
+    ) -> anyhow::Result<()> {
+        let file = file.as_ref();
+        // Un chemin absolu ou avec `..` sortirait du dossier de sortie
+        if !file
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            bail!(
+                "{} ({what}) is outside of {}",
+                file.display(),
+                self.dir.display()
+            );
+        }
+        if let Some(previous) = self.written.get(file) {
+            bail!(
+                "{} is written by both {previous} and {what}",
+                self.dir.join(file).display()
+            );
+        }
+        self.written.insert(file.to_path_buf(), what.to_string());
+        write(&self.dir.join(file), content)
+    }
+
+    /// Source of `file` if it was written, relative to the output directory.
+    pub fn source(&self, file: impl AsRef<Path>) -> Option<&str> {
+        self.written.get(file.as_ref()).map(String::as_str)
+    }
+}
+
+/// Writes `content` to `path`, creating the parent directories.
+pub fn write(path: &Path, content: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Recursively copies `from` into `to`, returning the number of copied files.
+pub fn copy_dir(from: &Path, to: &Path) -> anyhow::Result<usize> {
+    if !from.is_dir() {
+        return Ok(0);
+    }
+    let mut copied = 0;
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copied += copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("failed to copy {}", entry.path().display()))?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn output_dir_may_not_hold_the_project() {
+        let root = testing::dir("output-dir");
+        fs::create_dir_all(root.join("content/blog")).unwrap();
+        for out in [
+            ".",
+            "..",
+            "/",
+            "content",
+            "content/blog",
+            "templates/x",
+            "./src/../src",
+        ] {
+            assert!(check_output_dir(&root, Path::new(out)).is_err(), "{out}");
+        }
+        for out in ["dist", "./dist", "build/site", "contents"] {
+            assert!(check_output_dir(&root, Path::new(out)).is_ok(), "{out}");
+        }
+        assert!(check_output_dir(&root, &root).is_err());
+    }
+
+    #[test]
+    fn base_url_must_be_public() {
+        for url in [
+            "",
+            "/blog",
+            "example.com",
+            "ftp://example.com",
+            "http://localhost:8000",
+            "http://LOCALHOST",
+            "https://blog.localhost/",
+            "http://127.0.0.1:8000/",
+            "http://[::1]:8000",
+            "http://0.0.0.0",
+            "https://",
+        ] {
+            assert!(check_base_url(url).is_err(), "{url}");
+        }
+        for url in [
+            "https://example.com",
+            "https://example.com/blog/",
+            "http://user@example.com:8080",
+            "http://[2001:db8::1]:8000",
+        ] {
+            assert!(check_base_url(url).is_ok(), "{url}");
+        }
+    }
+
+    #[test]
+    fn output_files_are_written_once() {
+        let dir = testing::dir("output-once");
+        let mut output = Output::new(&dir);
+        output.write("index.html", "template index", "a").unwrap();
+        output
+            .write("blog/index.html", "content/blog/_index.md", "b")
+            .unwrap();
+        let err = output
+            .write("index.html", "content/_index.md", "c")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("template index and content/_index.md"),
+            "{err}"
+        );
+        assert_eq!(fs::read_to_string(dir.join("index.html")).unwrap(), "a");
+        assert_eq!(
+            output.source("blog/index.html"),
+            Some("content/blog/_index.md")
+        );
+    }
+
+    #[test]
+    fn unpublished_pages_are_audited() {
+        let site = testing::site(
+            "audit-unpublished",
+            &[
+                (
+                    "content/blog/later.md",
+                    "+++\ntitle = \"Later\"\ndate = \"2099-01-01\"\nog_type = \"bogus\"\n+++\n",
+                ),
+                (
+                    "content/blog/draft.md",
+                    "+++\ntitle = \"Draft\"\ndraft = true\n+++\n",
+                ),
+            ],
+        );
+        assert_eq!(site.pages().count(), 0);
+        let entries = site.seo_entries();
+        let status = |path: &str| entries.iter().find(|e| e.path == path).unwrap().status;
+        assert_eq!(status("/blog/later/"), Status::Scheduled);
+        assert_eq!(status("/blog/draft/"), Status::Draft);
+        let issues = audit::audit(&entries);
+        assert!(
+            issues.iter().any(|i| i.path == "/blog/later/"
+                && i.level == audit::Level::Warning
+                && i.message == "unknown og_type `bogus` (scheduled page)"),
+            "{issues:?}"
+        );
+    }
+
+    #[test]
+    fn a_new_project_builds() {
+        use crate::console::views::web::{INDEX_TEMPLATE, NOT_FOUND_TEMPLATE};
+        let site = testing::site(
+            "scaffold",
+            &[
+                (
+                    "rey.toml",
+                    "[site]\ntitle = \"Mine\"\nbase_url = \"https://example.com\"\n",
+                ),
+                ("templates/index.html.tera", INDEX_TEMPLATE),
+                ("templates/404.html.tera", NOT_FOUND_TEMPLATE),
+                // Sans template `section`, la section n'a pas de page
+                ("content/blog/hello.md", "+++\ntitle = \"Hello\"\n+++\n"),
+                ("templates/page.html.tera", "{{ page.title }}"),
+            ],
+        );
+        let out = testing::dir("scaffold-out");
+        let report = site.build(&out).unwrap();
+        assert_eq!(report.pages, 3);
+        let index = fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(index.contains("<h1>Mine</h1>"), "{index}");
+        assert!(out.join("404.html").exists());
+        assert_eq!(
+            fs::read_to_string(out.join("blog/hello/index.html")).unwrap(),
+            "Hello"
+        );
+        assert!(!out.join("blog/index.html").exists());
+        let sitemap = fs::read_to_string(out.join("sitemap.xml")).unwrap();
+        assert!(
+            !sitemap.contains("<loc>https://example.com/blog/</loc>"),
+            "{sitemap}"
+        );
+    }
+
+    #[test]
+    fn output_files_stay_in_the_output_directory() {
+        let dir = testing::dir("output-inside").join("dist");
+        let mut output = Output::new(&dir);
+        for file in ["../escaped/index.html", "a/../../b.html", "/tmp/abs.html"] {
+            let err = output.write(file, "alias", "x").unwrap_err().to_string();
+            assert!(err.contains("is outside of"), "{err}");
+        }
+        assert!(!dir.parent().unwrap().join("escaped").exists());
+        output.write("./a/index.html", "page", "x").unwrap();
+        assert!(dir.join("a/index.html").exists());
+    }
+
+    #[test]
+    fn page_files_of_paths() {
+        assert_eq!(page_file("/"), Path::new("index.html"));
+        assert_eq!(
+            page_file("/blog/hello/"),
+            Path::new("blog/hello/index.html")
+        );
+        assert_eq!(page_file("/404.html"), Path::new("404.html"));
+        assert_eq!(output_path("index").1, Path::new("index.html"));
+        assert_eq!(output_path("web/about").0, "/web/about/");
+        assert_eq!(output_path("docs/index").0, "/docs/");
+    }
+}
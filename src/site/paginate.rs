@@ -0,0 +1,115 @@
+//! Splits a listing (section or taxonomy term) into `/blog/`, `/blog/page/2/`…
+//! Each rendered page gets a [`Paginator`] in its context as `paginator`.
+
+use crate::{content::Page, render::helpers::Pagination};
+use serde::Serialize;
+
+/// The current page of a listing.
+#[derive(Debug, Serialize)]
+pub struct Paginator<'a> {
+    /// Pages listed on the current page.
+    pub pages: &'a [&'a Page],
+    /// Number of the current page, from 1.
+    pub current: usize,
+    /// Number of listing pages.
+    pub total: usize,
+    pub per_page: usize,
+    /// Number of listed pages, all listing pages included.
+    pub items: usize,
+    pub first: String,
+    pub last: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    /// Page numbers to show around the current one; ellipses have no number.
+    pub window: Vec<PagerLink>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PagerLink {
+    pub number: Option<usize>,
+    pub url: Option<String>,
+    pub current: bool,
+}
+
+/// URL path of the page `n` of the listing at `base`: `base` itself for the first
+/// one, then `pattern` with `:num` replaced, e.g. `/blog/page/2/`.
+pub fn page_url(base: &str, pattern: &str, n: usize) -> String {
+    if n <= 1 {
+        return base.to_string();
+    }
+    let rel = pattern
+        .trim_start_matches('/')
+        .replace(":num", &n.to_string());
+    let mut url = format!("{}/{rel}", base.trim_end_matches('/'));
+    if !url.ends_with('/') && !url.ends_with(".html") {
+        url.push('/');
+    }
+    url
+}
+
+/// Paginators of every page of the listing `pages`, `per_page` by page (all of them
+/// on one page for `0`), with their URL paths.
+pub fn paginate<'a>(
+    pages: &'a [&'a Page],
+    base: &str,
+    per_page: usize,
+    pattern: &str,
+    window: usize,
+) -> Vec<(String, Paginator<'a>)> {
+    let per_page = if per_page == 0 {
+        pages.len().max(1)
+    } else {
+        per_page
+    };
+    let total = Pagination::new(pages.len(), 1, per_page).pages;
+    let url = |n| page_url(base, pattern, n);
+    (1..=total)
+        .map(|n| {
+            let pagination = Pagination::new(pages.len(), n, per_page);
+            let paginator = Paginator {
+                pages: &pages[pagination.range(pages.len())],
+                current: n,
+                total,
+                per_page,
+                items: pages.len(),
+                first: url(1),
+                last: url(total),
+                prev: pagination.prev.then(|| url(n - 1)),
+                next: pagination.next.then(|| url(n + 1)),
+                window: pagination
+                    .window(window)
+                    .into_iter()
+                    .map(|number| PagerLink {
+                        number,
+                        url: number.map(url),
+                        current: number == Some(n),
+                    })
+                    .collect(),
+            };
+            (url(n), paginator)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_page_urls() {
+        assert_eq!(page_url("/blog/", "page/:num/", 1), "/blog/");
+        assert_eq!(page_url("/blog/", "page/:num/", 2), "/blog/page/2/");
+        assert_eq!(page_url("/blog/", "/p:num", 3), "/blog/p3/");
+        assert_eq!(page_url("/", "page-:num.html", 2), "/page-2.html");
+    }
+
+    #[test]
+    fn empty_listings_have_one_page() {
+        let listing = paginate(&[], "/blog/", 10, "page/:num/", 2);
+        assert_eq!(listing.len(), 1);
+        let (url, paginator) = &listing[0];
+        assert_eq!(url, "/blog/");
+        assert_eq!((paginator.current, paginator.total), (1, 1));
+        assert!(paginator.prev.is_none() && paginator.next.is_none());
+    }
+}
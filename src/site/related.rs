@@ -0,0 +1,125 @@
+//! Related pages, computed once per build.
+//!
+//! Pages are scored by the tags and `Seo::keywords` they share with the page and by
+//! a shared series, through an index of the pages by term, so that a page is only
+//! compared with the pages it has something in common with. The pages listed in
+//! the `related` front matter field come first.
+
+use crate::{config::RelatedConfig, content::Page, site::Site};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Related URL paths of every published page, by URL path.
+pub fn compute(site: &Site) -> HashMap<String, Vec<String>> {
+    let config = &site.config.related;
+    let pages: Vec<&Page> = site.pages().collect();
+    let mut related = HashMap::new();
+    if config.limit == 0 {
+        return related;
+    }
+
+    let terms: Vec<BTreeSet<String>> = pages.iter().map(|p| terms(p)).collect();
+    let mut by_term: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, page_terms) in terms.iter().enumerate() {
+        for term in page_terms {
+            by_term.entry(term).or_default().push(i);
+        }
+    }
+
+    for (i, page) in pages.iter().enumerate() {
+        // Une page citée deux fois, même sous deux noms, n'est listée qu'une fois
+        let mut seen: HashSet<&str> = HashSet::from([page.path.as_str()]);
+        let mut list: Vec<String> = page
+            .related
+            .iter()
+            .filter_map(|r| resolve(&pages, r))
+            .filter(|p| seen.insert(&p.path))
+            .map(|p| p.path.clone())
+            .collect();
+
+        let mut scores: HashMap<usize, usize> = HashMap::new();
+        for term in &terms[i] {
+            for &j in &by_term[term.as_str()] {
+                if j != i {
+                    *scores.entry(j).or_default() += weight(config, term);
+                }
+            }
+        }
+        let mut scored: Vec<(usize, usize)> = scores.into_iter().collect();
+        scored.sort_by(|(a, sa), (b, sb)| {
+            sb.cmp(sa)
+                .then_with(|| pages[*b].published().cmp(&pages[*a].published()))
+                .then_with(|| pages[*a].title.cmp(&pages[*b].title))
+        });
+        for (j, _) in scored {
+            if list.len() >= config.limit {
+                break;
+            }
+            if seen.insert(&pages[j].path) {
+                list.push(pages[j].path.clone());
+            }
+        }
+        list.truncate(config.limit);
+        related.insert(page.path.clone(), list);
+    }
+    related
+}
+
+/// Scored terms of a page: its slugified tags and keywords, and its series.
+fn terms(page: &Page) -> BTreeSet<String> {
+    let mut terms: BTreeSet<String> = page
+        .tags
+        .iter()
+        .chain(&page.seo.keywords)
+        .map(slug::slugify)
+        .filter(|t| !t.is_empty())
+        .collect();
+    if let Some(series) = &page.series {
+        terms.insert(format!("series:{}", slug::slugify(series)));
+    }
+    terms
+}
+
+fn weight(config: &RelatedConfig, term: &str) -> usize {
+    if term.starts_with("series:") {
+        config.series_weight
+    } else {
+        config.term_weight
+    }
+}
+
+/// Page named in a `related` field by URL path, slug or source file.
+fn resolve<'a>(pages: &[&'a Page], name: &str) -> Option<&'a Page> {
+    let name = name.trim();
+    let path = format!("/{}/", name.trim_matches('/'));
+    pages
+        .iter()
+        .find(|p| p.path == path || p.slug == name || p.source == name)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    #[test]
+    fn related_pages_are_listed_once() {
+        let site = testing::site(
+            "related-once",
+            &[
+                ("rey.toml", "[related]\nlimit = 3\n"),
+                (
+                    "content/blog/a.md",
+                    "+++\ntags = [\"rust\"]\nrelated = [\"b\", \"/blog/c/\", \"blog/b.md\", \"a\"]\n+++\n",
+                ),
+                ("content/blog/b.md", "+++\ntags = [\"rust\"]\n+++\n"),
+                ("content/blog/c.md", "+++\ntags = [\"web\"]\n+++\n"),
+                ("content/blog/d.md", "+++\ntags = [\"rust\"]\n+++\n"),
+            ],
+        );
+        assert_eq!(
+            site.related["/blog/a/"],
+            ["/blog/b/", "/blog/c/", "/blog/d/"]
+        );
+        assert_eq!(site.related["/blog/c/"], Vec::<String>::new());
+    }
+}
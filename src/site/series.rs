@@ -0,0 +1,174 @@
+//! Series of posts grouped by the `series` front matter field, ordered by `part`
+//! then by date. Each series gets a landing page at `/series/{slug}/` when the
+//! `series` template exists.
+
+use crate::{content::Page, site::Site};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub const SERIES_TEMPLATE: &str = "series";
+pub const SERIES_DIR: &str = "series";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Series {
+    pub title: String,
+    pub slug: String,
+    /// URL path of the landing page, e.g. `/series/rocket-from-scratch/`.
+    pub path: String,
+    /// URL paths of the parts, in reading order.
+    #[serde(skip)]
+    pub pages: Vec<String>,
+}
+
+/// A series as seen from one of its parts (or from its landing page, without
+/// position), exposed to templates as `series`.
+#[derive(Debug, Serialize)]
+pub struct SeriesContext<'a> {
+    pub title: &'a str,
+    pub path: &'a str,
+    /// Absolute URL of the landing page.
+    pub url: String,
+    pub pages: Vec<&'a Page>,
+    /// Number of parts.
+    pub total: usize,
+    /// Position of the current page, from 1.
+    pub position: Option<usize>,
+    pub prev: Option<&'a Page>,
+    pub next: Option<&'a Page>,
+}
+
+/// Groups the published pages by series, by slug of the series name, which is
+/// spelled as in the first part.
+pub fn collect(site: &Site) -> Vec<Series> {
+    let mut series: BTreeMap<String, Vec<&Page>> = BTreeMap::new();
+    for page in site.pages() {
+        if let Some(name) = &page.series {
+            series.entry(slug::slugify(name)).or_default().push(page);
+        }
+    }
+    series
+        .into_iter()
+        .map(|(slug, mut pages)| {
+            pages.sort_by(|a, b| {
+                a.part
+                    .unwrap_or(usize::MAX)
+                    .cmp(&b.part.unwrap_or(usize::MAX))
+                    .then_with(|| a.published().cmp(&b.published()))
+                    .then_with(|| a.title.cmp(&b.title))
+            });
+            Series {
+                path: format!("/{SERIES_DIR}/{slug}/"),
+                title: pages[0].series.clone().unwrap_or_default(),
+                slug,
+                pages: pages.iter().map(|p| p.path.clone()).collect(),
+            }
+        })
+        .collect()
+}
+
+impl Series {
+    /// Context of the series for `current`, or for the landing page when `None`.
+    pub fn context<'a>(&'a self, site: &'a Site, current: Option<&str>) -> SeriesContext<'a> {
+        let pages: Vec<&Page> = self.pages.iter().filter_map(|p| site.page(p)).collect();
+        let index = current.and_then(|c| pages.iter().position(|p| p.path == c));
+        SeriesContext {
+            title: &self.title,
+            path: &self.path,
+            url: site.config.url(&self.path),
+            total: pages.len(),
+            position: index.map(|i| i + 1),
+            prev: index.and_then(|i| i.checked_sub(1)).map(|i| pages[i]),
+            next: index.and_then(|i| pages.get(i + 1).copied()),
+            pages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    fn site() -> crate::site::Site {
+        testing::site(
+            "series",
+            &[
+                ("rey.toml", "[site]\nbase_url = \"https://example.com\"\n"),
+                (
+                    "content/blog/intro.md",
+                    "+++\nseries = \"Rocket from scratch\"\npart = 1\ndate = \"2024-05-01\"\n+++\n",
+                ),
+                (
+                    "content/blog/routes.md",
+                    "+++\nseries = \"Rocket from scratch\"\npart = 2\ndate = \"2024-01-01\"\n+++\n",
+                ),
+                // Sans numéro, les parties suivent par date
+                (
+                    "content/blog/late.md",
+                    "+++\nseries = \"rocket-from-scratch\"\ndate = \"2024-09-01\"\n+++\n",
+                ),
+                (
+                    "content/blog/early.md",
+                    "+++\nseries = \"Rocket from scratch\"\ndate = \"2024-02-01\"\n+++\n",
+                ),
+                (
+                    "content/blog/draft.md",
+                    "+++\nseries = \"Rocket from scratch\"\npart = 3\ndraft = true\n+++\n",
+                ),
+                ("content/blog/alone.md", "+++\nseries = \" \"\n+++\n"),
+            ],
+        )
+    }
+
+    #[test]
+    fn parts_are_ordered_by_part_then_date() {
+        let site = site();
+        assert_eq!(site.series.len(), 1);
+        let series = &site.series[0];
+        assert_eq!(series.slug, "rocket-from-scratch");
+        assert_eq!(series.path, "/series/rocket-from-scratch/");
+        assert_eq!(
+            series.pages,
+            [
+                "/blog/intro/",
+                "/blog/routes/",
+                "/blog/early/",
+                "/blog/late/"
+            ]
+        );
+    }
+
+    #[test]
+    fn parts_link_to_their_neighbours() {
+        let site = site();
+        let series = &site.series[0];
+        let path = |p: Option<&crate::content::Page>| p.map(|p| p.path.clone());
+
+        let first = series.context(&site, Some("/blog/intro/"));
+        assert_eq!((first.position, first.total), (Some(1), 4));
+        assert_eq!(path(first.prev), None);
+        assert_eq!(path(first.next).as_deref(), Some("/blog/routes/"));
+
+        let third = series.context(&site, Some("/blog/early/"));
+        assert_eq!(third.position, Some(3));
+        assert_eq!(path(third.prev).as_deref(), Some("/blog/routes/"));
+        assert_eq!(path(third.next).as_deref(), Some("/blog/late/"));
+
+        let last = series.context(&site, Some("/blog/late/"));
+        assert_eq!(path(last.next), None);
+
+        let page = site.page("/blog/early/").unwrap();
+        assert_eq!(site.series_of(page).unwrap().slug, "rocket-from-scratch");
+        assert!(site.series_of(site.page("/blog/alone/").unwrap()).is_none());
+    }
+
+    #[test]
+    fn the_landing_page_lists_every_part() {
+        let site = site();
+        let index = site.series[0].context(&site, None);
+        assert_eq!(index.title, "Rocket from scratch");
+        assert_eq!(index.url, "https://example.com/series/rocket-from-scratch/");
+        assert_eq!((index.position, index.total), (None, 4));
+        assert!(index.prev.is_none() && index.next.is_none());
+        assert_eq!(index.pages[0].path, "/blog/intro/");
+    }
+}
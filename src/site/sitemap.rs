@@ -0,0 +1,317 @@
+//! `sitemap.xml` and `robots.txt`, configured by `[sitemap]` and `[robots]`.
+//!
+//! Pages whose `Seo` is `noindex` or whose front matter holds `sitemap = false` are
+//! left out. Past [`MAX_URLS`] URLs, `sitemap.xml` becomes a sitemap index of
+//! `sitemap-1.xml`, `sitemap-2.xml`…
+
+use crate::{
+    Seo,
+    content::parse_date,
+    render::helpers::escape,
+    site::{Output, Site, output_path, series, taxonomies},
+};
+use chrono::SecondsFormat;
+use serde_json::{Map, Value};
+use std::fmt::Write as _;
+
+pub const SITEMAP_FILE: &str = "sitemap.xml";
+pub const ROBOTS_FILE: &str = "robots.txt";
+pub const SITEMAP_NS: &str = "http://www.sitemaps.org/schemas/sitemap/0.9";
+/// Limit of URLs per sitemap file set by the protocol.
+pub const MAX_URLS: usize = 50_000;
+
+/// One `<url>` of the sitemap.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Entry {
+    pub loc: String,
+    /// W3C datetime, from `Seo::updated`.
+    pub lastmod: Option<String>,
+}
+
+/// Whether a page may be listed: not `noindex` and no `sitemap = false`.
+pub fn listed(seo: &Seo, extra: &Map<String, Value>) -> bool {
+    !seo.is_noindex() && extra.get("sitemap").and_then(Value::as_bool) != Some(false)
+}
+
+/// `Seo::updated` as a W3C datetime; invalid dates are dropped.
+fn lastmod(seo: &Seo) -> Option<String> {
+    seo.updated
+        .as_deref()
+        .and_then(parse_date)
+        .map(|d| d.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+fn excluded(site: &Site, path: &str) -> bool {
+    site.config
+        .sitemap
+        .exclude
+        .iter()
+        .any(|e| match e.strip_suffix('*') {
+            Some(prefix) => path.starts_with(prefix),
+            None => path == e,
+        })
+}
+
+/// Every URL of the sitemap, sorted.
+pub fn entries(site: &Site) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut push = |path: &str, seo: &Seo| {
+        if !excluded(site, path) {
+            entries.push(Entry {
+                loc: site.config.url(path),
+                lastmod: lastmod(seo),
+            });
+        }
+    };
+
+    for name in site.page_templates() {
+        let (path, _) = output_path(&name);
+        let seo = site.seo(&path);
+        if !seo.is_noindex() {
+            push(&path, &seo);
+        }
+    }
+    for section in &site.sections {
+        if site.section_template(section).is_none() || !listed(&section.seo, &section.extra) {
+            continue;
+        }
+        // Sans date propre, une section date de sa page la plus récente
+        let mut seo = section.seo.clone();
+        if seo.updated.is_none() {
+            seo.updated = section
+                .pages
+                .iter()
+                .filter_map(|p| p.seo.updated.as_deref())
+                .max_by_key(|u| parse_date(u))
+                .map(str::to_string);
+        }
+        push(&section.path, &seo);
+    }
+    for taxonomy in &site.taxonomies {
+        if site.has_template(&taxonomy.template) && !site.seo(&taxonomy.path).is_noindex() {
+            push(&taxonomy.path, &site.seo(&taxonomy.path));
+        }
+        if !site.has_template(&taxonomy.term_template) {
+            continue;
+        }
+        for term in &taxonomy.terms {
+            let mut seo = site.seo(&term.path);
+            seo.updated = taxonomies::pages(site, term)
+                .iter()
+                .filter_map(|p| p.seo.updated.as_deref())
+                .max_by_key(|u| parse_date(u))
+                .map(str::to_string);
+            if !seo.is_noindex() {
+                push(&term.path, &seo);
+            }
+        }
+    }
+    if site.has_template(series::SERIES_TEMPLATE) {
+        for series in &site.series {
+            let seo = site.seo(&series.path);
+            if !seo.is_noindex() {
+                push(&series.path, &seo);
+            }
+        }
+    }
+    for page in site.pages() {
+        if !page.draft && listed(&page.seo, &page.extra) {
+            push(&page.path, &page.seo);
+        }
+    }
+
+    entries.sort();
+    entries.dedup_by(|a, b| a.loc == b.loc);
+    entries
+}
+
+/// Writes the sitemap(s) and `robots.txt` into `out`, returning the number of files.
+pub fn write_all(site: &Site, output: &mut Output) -> anyhow::Result<usize> {
+    let mut files = 0;
+    if site.config.sitemap.enabled {
+        for (file, xml) in sitemaps(site, &entries(site)) {
+            output.write(file, "sitemap", &xml)?;
+            files += 1;
+        }
+    }
+    if site.config.robots.enabled {
+        output.write(ROBOTS_FILE, "robots.txt", &robots(site))?;
+        files += 1;
+    }
+    Ok(files)
+}
+
+/// Sitemap files of `entries` and their XML: `sitemap.xml` alone, or past
+/// [`MAX_URLS`] the `sitemap-N.xml` parts followed by the `sitemap.xml` index.
+pub fn sitemaps(site: &Site, entries: &[Entry]) -> Vec<(String, String)> {
+    if entries.len() <= MAX_URLS {
+        return vec![(SITEMAP_FILE.to_string(), urlset(site, entries))];
+    }
+    let mut files = Vec::new();
+    let mut parts = Vec::new();
+    for (i, chunk) in entries.chunks(MAX_URLS).enumerate() {
+        let file = format!("sitemap-{}.xml", i + 1);
+        parts.push(Entry {
+            loc: site.config.url(&file),
+            lastmod: chunk.iter().filter_map(|e| e.lastmod.clone()).max(),
+        });
+        files.push((file, urlset(site, chunk)));
+    }
+    files.push((SITEMAP_FILE.to_string(), index(&parts)));
+    files
+}
+
+/// `<urlset>` of a sitemap file.
+pub fn urlset(site: &Site, entries: &[Entry]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, r#"<urlset xmlns="{SITEMAP_NS}">"#);
+    for entry in entries {
+        xml.push_str("<url>\n");
+        let _ = writeln!(xml, "<loc>{}</loc>", escape(&entry.loc));
+        if let Some(lastmod) = &entry.lastmod {
+            let _ = writeln!(xml, "<lastmod>{lastmod}</lastmod>");
+        }
+        if let Some(freq) = &site.config.sitemap.changefreq {
+            let _ = writeln!(xml, "<changefreq>{}</changefreq>", escape(freq));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// `<sitemapindex>` pointing to the sitemap files.
+pub fn index(parts: &[Entry]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, r#"<sitemapindex xmlns="{SITEMAP_NS}">"#);
+    for part in parts {
+        xml.push_str("<sitemap>\n");
+        let _ = writeln!(xml, "<loc>{}</loc>", escape(&part.loc));
+        if let Some(lastmod) = &part.lastmod {
+            let _ = writeln!(xml, "<lastmod>{lastmod}</lastmod>");
+        }
+        xml.push_str("</sitemap>\n");
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+/// `robots.txt`: one group per `[[robots.rules]]`, then the sitemap URL.
+pub fn robots(site: &Site) -> String {
+    let mut txt = String::new();
+    for rule in &site.config.robots.rules {
+        let _ = writeln!(txt, "User-agent: {}", rule.user_agent);
+        for path in &rule.allow {
+            let _ = writeln!(txt, "Allow: {path}");
+        }
+        for path in &rule.disallow {
+            let _ = writeln!(txt, "Disallow: {path}");
+        }
+        if rule.allow.is_empty() && rule.disallow.is_empty() {
+            txt.push_str("Disallow:\n");
+        }
+        if let Some(delay) = rule.crawl_delay {
+            let _ = writeln!(txt, "Crawl-delay: {delay}");
+        }
+        txt.push('\n');
+    }
+    if site.config.sitemap.enabled {
+        let _ = writeln!(txt, "Sitemap: {}", site.config.url(SITEMAP_FILE));
+    }
+    txt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const CONFIG: &str = "[site]\nbase_url = \"https://example.com/docs/\"\n";
+
+    fn locs(site: &Site) -> Vec<String> {
+        entries(site).into_iter().map(|e| e.loc).collect()
+    }
+
+    #[test]
+    fn hidden_pages_are_left_out() {
+        let site = testing::site(
+            "sitemap-hidden",
+            &[
+                (
+                    "rey.toml",
+                    &format!("{CONFIG}[sitemap]\nexclude = [\"/blog/private/*\"]\n"),
+                ),
+                ("templates/section.html.tera", ""),
+                ("templates/about.html.tera", ""),
+                (
+                    "content/blog/a.md",
+                    "+++\nupdated = \"2025-01-31 08:30:00\"\n+++\n",
+                ),
+                ("content/blog/b.md", "+++\nnoindex = true\n+++\n"),
+                ("content/blog/c.md", "+++\nsitemap = false\n+++\n"),
+                ("content/blog/private/d.md", ""),
+                ("content/blog/e.md", "+++\ndraft = true\n+++\n"),
+            ],
+        );
+        assert_eq!(
+            locs(&site),
+            [
+                "https://example.com/docs/about/",
+                "https://example.com/docs/blog/",
+                "https://example.com/docs/blog/a/",
+            ]
+        );
+        // La section date de sa page la plus récente
+        let entries = entries(&site);
+        assert_eq!(entries[1].lastmod.as_deref(), Some("2025-01-31T08:30:00Z"));
+        let xml = urlset(&site, &entries);
+        assert!(xml.contains("<loc>https://example.com/docs/blog/a/</loc>\n<lastmod>2025-01-31T08:30:00Z</lastmod>"), "{xml}");
+    }
+
+    #[test]
+    fn large_sitemaps_are_split() {
+        let site = testing::site("sitemap-split", &[("rey.toml", CONFIG)]);
+        let entries: Vec<Entry> = (0..=MAX_URLS)
+            .map(|i| Entry {
+                loc: site.config.url(&format!("/p{i}/")),
+                lastmod: (i == 7).then(|| "2025-01-31T00:00:00Z".to_string()),
+            })
+            .collect();
+        assert_eq!(sitemaps(&site, &entries[..MAX_URLS]).len(), 1);
+
+        let files = sitemaps(&site, &entries);
+        let names: Vec<&str> = files.iter().map(|(f, _)| f.as_str()).collect();
+        assert_eq!(names, ["sitemap-1.xml", "sitemap-2.xml", "sitemap.xml"]);
+        assert_eq!(files[0].1.matches("<url>").count(), MAX_URLS);
+        assert_eq!(files[1].1.matches("<url>").count(), 1);
+        let index = &files[2].1;
+        assert!(
+            index.starts_with("<?xml") && index.contains("<sitemapindex"),
+            "{index}"
+        );
+        assert!(index.contains("<loc>https://example.com/docs/sitemap-1.xml</loc>\n<lastmod>2025-01-31T00:00:00Z</lastmod>"));
+        assert!(index.contains("<loc>https://example.com/docs/sitemap-2.xml</loc>\n</sitemap>"));
+    }
+
+    #[test]
+    fn robots_txt_follows_the_config() {
+        let site = testing::site("robots-default", &[("rey.toml", CONFIG)]);
+        assert_eq!(
+            robots(&site),
+            "User-agent: *\nDisallow:\n\nSitemap: https://example.com/docs/sitemap.xml\n"
+        );
+        let site = testing::site(
+            "robots-rules",
+            &[(
+                "rey.toml",
+                &format!(
+                    "{CONFIG}[sitemap]\nenabled = false\n[[robots.rules]]\nuser_agent = \"GPTBot\"\ndisallow = [\"/\"]\n[[robots.rules]]\nallow = [\"/\"]\ndisallow = [\"/admin/\"]\ncrawl_delay = 5\n"
+                ),
+            )],
+        );
+        assert_eq!(
+            robots(&site),
+            "User-agent: GPTBot\nDisallow: /\n\nUser-agent: *\nAllow: /\nDisallow: /admin/\nCrawl-delay: 5\n\n"
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Taxonomies configured by `[[taxonomies]]`: `/tags/` lists the terms of `tags`
+//! and `/tags/rust/` (then `/tags/rust/page/2/`…, see [`super::paginate`]) the
+//! pages of one term.
+//!
+//! Terms are grouped by slug, so « Rust » and « rust » are the same term.
+
+use crate::{
+    config::TaxonomyConfig,
+    content::Page,
+    site::{
+        Site,
+        feeds::{self, FeedLink},
+    },
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+pub const TAXONOMY_TEMPLATE: &str = "taxonomy";
+pub const TERM_TEMPLATE: &str = "term";
+
+/// A taxonomy and its terms, exposed to every template in `taxonomies`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Taxonomy {
+    pub name: String,
+    pub title: String,
+    /// URL path of the term index, e.g. `/tags/`.
+    pub path: String,
+    /// Terms sorted by name.
+    pub terms: Vec<Term>,
+    #[serde(skip)]
+    pub template: String,
+    #[serde(skip)]
+    pub term_template: String,
+    #[serde(skip)]
+    pub paginate: usize,
+    #[serde(skip)]
+    pub paginate_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Term {
+    pub name: String,
+    pub slug: String,
+    pub path: String,
+    /// Number of published pages holding the term.
+    pub count: usize,
+    /// Feeds of the term, when `[feeds] taxonomies` lists its taxonomy.
+    pub feeds: Vec<FeedLink>,
+    /// URL paths of the pages, newest first.
+    #[serde(skip)]
+    pub pages: Vec<String>,
+}
+
+impl Taxonomy {
+    fn new(config: &TaxonomyConfig) -> Self {
+        let title = config.title.clone().unwrap_or_else(|| {
+            let mut chars = config.name.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        });
+        Self {
+            name: config.name.clone(),
+            title,
+            path: format!("/{}/", slug::slugify(&config.name)),
+            terms: Vec::new(),
+            template: config
+                .template
+                .clone()
+                .unwrap_or_else(|| TAXONOMY_TEMPLATE.to_string()),
+            term_template: config
+                .term_template
+                .clone()
+                .unwrap_or_else(|| TERM_TEMPLATE.to_string()),
+            paginate: config.paginate.max(1),
+            paginate_path: config.paginate_path.clone(),
+        }
+    }
+}
+
+/// Collects the terms of every configured taxonomy from the published pages.
+pub fn collect(site: &Site) -> Vec<Taxonomy> {
+    let mut published: Vec<&Page> = site.pages().collect();
+    published.sort_by(|a, b| {
+        b.published()
+            .cmp(&a.published())
+            .then_with(|| a.title.cmp(&b.title))
+    });
+    site.config
+        .taxonomies
+        .iter()
+        .map(|config| {
+            let mut taxonomy = Taxonomy::new(config);
+            let mut terms: BTreeMap<String, Term> = BTreeMap::new();
+            for page in &published {
+                // Une page ne compte qu'une fois par terme, même nommé deux fois
+                let mut seen = HashSet::new();
+                for name in page.terms(&taxonomy.name) {
+                    let slug = slug::slugify(&name);
+                    if slug.is_empty() || !seen.insert(slug.clone()) {
+                        continue;
+                    }
+                    let term = terms.entry(slug.clone()).or_insert_with(|| Term {
+                        path: feeds::term_dir(&taxonomy.name, &name),
+                        name,
+                        slug,
+                        count: 0,
+                        feeds: Vec::new(),
+                        pages: Vec::new(),
+                    });
+                    term.pages.push(page.path.clone());
+                    term.count += 1;
+                }
+            }
+            let syndicated = site.config.feeds.taxonomies.contains(&taxonomy.name);
+            for term in terms.values_mut() {
+                // Le flux d'un terme n'existe que s'il a des pages datées
+                if syndicated && !feeds::select(site, pages(site, term)).is_empty() {
+                    term.feeds =
+                        feeds::links(site, &term.path, &feeds::term_title(site, &term.name));
+                }
+            }
+            taxonomy.terms = terms.into_values().collect();
+            taxonomy
+                .terms
+                .sort_by_key(|t| (t.name.to_lowercase(), t.slug.clone()));
+            taxonomy
+        })
+        .collect()
+}
+
+/// Pages of a term, in the order of [`Term::pages`].
+pub fn pages<'a>(site: &'a Site, term: &Term) -> Vec<&'a Page> {
+    term.pages
+        .iter()
+        .filter_map(|path| site.page(path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const CONFIG: &str =
+        "[site]\nbase_url = \"https://example.com\"\n[feeds]\ntaxonomies = [\"tags\"]\n";
+
+    #[test]
+    fn terms_count_each_page_once() {
+        let site = testing::site(
+            "taxonomy-terms",
+            &[
+                ("rey.toml", CONFIG),
+                (
+                    "content/blog/a.md",
+                    "+++\ntitle = \"A\"\ndate = 2025-01-02\ntags = [\"Rust\", \"rust\", \"Web\"]\n+++\n",
+                ),
+                (
+                    "content/blog/b.md",
+                    "+++\ntitle = \"B\"\ndate = 2025-01-03\ntags = [\"rust\"]\n+++\n",
+                ),
+                (
+                    "content/blog/c.md",
+                    "+++\ntitle = \"C\"\ntags = [\"web\"]\n+++\n",
+                ),
+            ],
+        );
+        let tags = site.taxonomies.iter().find(|t| t.name == "tags").unwrap();
+        let terms: Vec<_> = tags
+            .terms
+            .iter()
+            .map(|t| (t.slug.as_str(), t.count, t.pages.clone()))
+            .collect();
+        assert_eq!(
+            terms,
+            [
+                (
+                    "rust",
+                    2,
+                    vec!["/blog/b/".to_string(), "/blog/a/".to_string()]
+                ),
+                (
+                    "web",
+                    2,
+                    vec!["/blog/a/".to_string(), "/blog/c/".to_string()]
+                ),
+            ]
+        );
+        assert!(tags.terms.iter().all(|t| !t.feeds.is_empty()));
+
+        let rust = &tags.terms[0];
+        let entries = feeds::select(&site, pages(&site, rust));
+        let titles: Vec<_> = entries.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, ["B", "A"]);
+    }
+
+    #[test]
+    fn undated_terms_have_no_feed() {
+        let site = testing::site(
+            "taxonomy-undated",
+            &[
+                ("rey.toml", CONFIG),
+                (
+                    "content/docs/a.md",
+                    "+++\ntitle = \"A\"\ntags = [\"guide\"]\n+++\n",
+                ),
+            ],
+        );
+        let tags = site.taxonomies.iter().find(|t| t.name == "tags").unwrap();
+        assert_eq!(tags.terms.len(), 1);
+        assert!(tags.terms[0].feeds.is_empty());
+    }
+}